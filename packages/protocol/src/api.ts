@@ -157,7 +157,7 @@ export const SystemGetFrontmostApplicationMessageSchema = z.object({
 
 export const SystemShowInFinderPayloadSchema = z.object({
 	requestId: z.string(),
-	path: z.string()
+	paths: z.array(z.string())
 });
 export const SystemShowInFinderMessageSchema = z.object({
 	type: z.literal('system-show-in-finder'),
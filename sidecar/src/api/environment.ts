@@ -84,8 +84,9 @@ export async function getFrontmostApplication(): Promise<Application> {
 	return sendRequest<Application>('system-get-frontmost-application');
 }
 
-export async function showInFinder(path: fs.PathLike): Promise<void> {
-	return sendRequest<void>('system-show-in-finder', { path: path.toString() });
+export async function showInFinder(path: fs.PathLike | fs.PathLike[]): Promise<void> {
+	const paths = (Array.isArray(path) ? path : [path]).map((p) => p.toString());
+	return sendRequest<void>('system-show-in-finder', { paths });
 }
 
 export async function trash(path: fs.PathLike | fs.PathLike[]): Promise<void> {
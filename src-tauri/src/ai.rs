@@ -1,4 +1,5 @@
 use crate::error::AppError;
+use crate::migrations::{self, Migration};
 use futures_util::StreamExt;
 use once_cell::sync::Lazy;
 use rusqlite::{params, Connection, Result as RusqliteResult};
@@ -222,6 +223,20 @@ pub fn ai_can_access(app: tauri::AppHandle) -> Result<bool, String> {
     is_ai_api_key_set()
 }
 
+pub(crate) const MIGRATIONS: &[Migration] = &[Migration::sql(
+    "create generations table",
+    "CREATE TABLE IF NOT EXISTS ai_generations (
+        id TEXT PRIMARY KEY,
+        created INTEGER NOT NULL,
+        model TEXT NOT NULL,
+        tokens_prompt INTEGER NOT NULL,
+        tokens_completion INTEGER NOT NULL,
+        native_tokens_prompt INTEGER NOT NULL,
+        native_tokens_completion INTEGER NOT NULL,
+        total_cost REAL NOT NULL
+    )",
+)];
+
 pub struct AiUsageManager {
     db: Mutex<Connection>,
 }
@@ -237,22 +252,9 @@ impl AiUsageManager {
         Ok(Self { db: Mutex::new(db) })
     }
 
-    pub fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS ai_generations (
-                id TEXT PRIMARY KEY,
-                created INTEGER NOT NULL,
-                model TEXT NOT NULL,
-                tokens_prompt INTEGER NOT NULL,
-                tokens_completion INTEGER NOT NULL,
-                native_tokens_prompt INTEGER NOT NULL,
-                native_tokens_completion INTEGER NOT NULL,
-                total_cost REAL NOT NULL
-            )",
-            [],
-        )?;
-        Ok(())
+    pub fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)
     }
 
     pub fn log_generation(&self, data: &GenerationData) -> Result<(), AppError> {
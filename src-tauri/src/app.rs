@@ -1,11 +1,30 @@
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppAction {
+    pub id: String,
+    pub name: String,
+    pub exec: Option<String>,
+    pub icon_path: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct App {
     pub name: String,
     pub comment: Option<String>,
+    pub generic_name: Option<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub mime_types: Vec<String>,
     pub exec: Option<String>,
+    pub icon: Option<String>,
     pub icon_path: Option<String>,
+    pub terminal: bool,
+    pub working_directory: Option<String>,
+    pub startup_wm_class: Option<String>,
+    pub desktop_file_id: Option<String>,
+    pub desktop_file_path: Option<String>,
+    pub actions: Vec<AppAction>,
 }
 
 impl App {
@@ -13,8 +32,19 @@ impl App {
         Self {
             name,
             comment: None,
+            generic_name: None,
+            keywords: Vec::new(),
+            categories: Vec::new(),
+            mime_types: Vec::new(),
             exec: None,
+            icon: None,
             icon_path: None,
+            terminal: false,
+            working_directory: None,
+            startup_wm_class: None,
+            desktop_file_id: None,
+            desktop_file_path: None,
+            actions: Vec::new(),
         }
     }
 
@@ -23,13 +53,68 @@ impl App {
         self
     }
 
+    pub fn with_generic_name(mut self, generic_name: Option<String>) -> Self {
+        self.generic_name = generic_name;
+        self
+    }
+
+    pub fn with_keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    pub fn with_categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn with_mime_types(mut self, mime_types: Vec<String>) -> Self {
+        self.mime_types = mime_types;
+        self
+    }
+
     pub fn with_exec(mut self, exec: Option<String>) -> Self {
         self.exec = exec;
         self
     }
 
+    pub fn with_icon(mut self, icon: Option<String>) -> Self {
+        self.icon = icon;
+        self
+    }
+
     pub fn with_icon_path(mut self, icon_path: Option<String>) -> Self {
         self.icon_path = icon_path;
         self
     }
+
+    pub fn with_terminal(mut self, terminal: bool) -> Self {
+        self.terminal = terminal;
+        self
+    }
+
+    pub fn with_working_directory(mut self, working_directory: Option<String>) -> Self {
+        self.working_directory = working_directory;
+        self
+    }
+
+    pub fn with_startup_wm_class(mut self, startup_wm_class: Option<String>) -> Self {
+        self.startup_wm_class = startup_wm_class;
+        self
+    }
+
+    pub fn with_desktop_file_id(mut self, desktop_file_id: Option<String>) -> Self {
+        self.desktop_file_id = desktop_file_id;
+        self
+    }
+
+    pub fn with_desktop_file_path(mut self, desktop_file_path: Option<String>) -> Self {
+        self.desktop_file_path = desktop_file_path;
+        self
+    }
+
+    pub fn with_actions(mut self, actions: Vec<AppAction>) -> Self {
+        self.actions = actions;
+        self
+    }
 }
@@ -0,0 +1,219 @@
+use crate::{cache::AppCache, desktop::DesktopFileManager, error::AppError, icons::IconResolver};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Emitter, Manager};
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedValue,
+};
+
+// settings portal keys holding the icon theme, for GNOME and KDE
+const ICON_THEME_SETTINGS: [(&str, &str); 2] = [
+    ("org.gnome.desktop.interface", "icon-theme"),
+    ("org.kde.kdeglobals.Icons", "Theme"),
+];
+
+// wrapped so it doesn't clash with the file search debouncer in managed state
+struct AppDirectoryWatcher {
+    watches: Mutex<Watches>,
+}
+
+struct Watches {
+    debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    app_dirs: Vec<PathBuf>,
+    /// App directories that exist, watched recursively.
+    watched: HashSet<PathBuf>,
+    /// The closest existing ancestors of missing app directories, watched on
+    /// their own so the directories are picked up once they are created.
+    ancestors: HashSet<PathBuf>,
+}
+
+fn nearest_existing_ancestor(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.is_dir())
+        .map(Path::to_path_buf)
+}
+
+impl Watches {
+    /// Starts watching app directories that appeared and falls back to their
+    /// ancestors for ones that are missing. Returns the directories that
+    /// appeared.
+    fn sync(&mut self) -> Vec<PathBuf> {
+        let mut appeared = Vec::new();
+        // a directory can be created between checking for it and watching its
+        // parent, so check again whenever the watched ancestors change
+        loop {
+            appeared.extend(self.sync_app_dirs());
+            if !self.sync_ancestors() {
+                return appeared;
+            }
+        }
+    }
+
+    fn sync_app_dirs(&mut self) -> Vec<PathBuf> {
+        let mut appeared = Vec::new();
+        for dir in &self.app_dirs {
+            let exists = dir.is_dir();
+            if exists == self.watched.contains(dir) {
+                continue;
+            }
+            if exists {
+                match self
+                    .debouncer
+                    .watcher()
+                    .watch(dir, RecursiveMode::Recursive)
+                {
+                    Ok(()) => {
+                        self.debouncer
+                            .cache()
+                            .add_root(dir, RecursiveMode::Recursive);
+                        self.watched.insert(dir.clone());
+                        appeared.push(dir.clone());
+                    }
+                    Err(e) => eprintln!("Failed to watch {}: {:?}", dir.display(), e),
+                }
+            } else {
+                let _ = self.debouncer.watcher().unwatch(dir);
+                self.debouncer.cache().remove_root(dir);
+                self.watched.remove(dir);
+            }
+        }
+        appeared
+    }
+
+    /// Returns whether the set of watched ancestors changed.
+    fn sync_ancestors(&mut self) -> bool {
+        let wanted: HashSet<PathBuf> = self
+            .app_dirs
+            .iter()
+            .filter(|dir| !self.watched.contains(*dir))
+            .filter_map(|dir| nearest_existing_ancestor(dir))
+            // events inside watched app directories arrive anyway
+            .filter(|ancestor| !self.watched.iter().any(|dir| ancestor.starts_with(dir)))
+            .collect();
+        if wanted == self.ancestors {
+            return false;
+        }
+
+        for ancestor in self.ancestors.difference(&wanted) {
+            let _ = self.debouncer.watcher().unwatch(ancestor);
+        }
+        for ancestor in wanted.difference(&self.ancestors) {
+            if let Err(e) = self
+                .debouncer
+                .watcher()
+                .watch(ancestor, RecursiveMode::NonRecursive)
+            {
+                eprintln!("Failed to watch {}: {:?}", ancestor.display(), e);
+            }
+        }
+        self.ancestors = wanted;
+        true
+    }
+}
+
+fn handle_changes(app_handle: &AppHandle, paths: Vec<PathBuf>) {
+    match AppCache::apply_changes(&paths) {
+        Ok(apps) => {
+            if let Err(e) = app_handle.emit("apps-changed", &apps) {
+                eprintln!("Failed to emit apps-changed event: {:?}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to update app cache: {:?}", e),
+    }
+}
+
+/// Rescans the apps whenever the settings portal reports a new icon theme.
+fn watch_icon_theme(app_handle: &AppHandle) -> zbus::Result<()> {
+    let connection = Connection::session()?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Settings",
+    )?;
+
+    for message in proxy.receive_signal("SettingChanged")? {
+        let Ok((namespace, key, _)) = message.body().deserialize::<(String, String, OwnedValue)>()
+        else {
+            continue;
+        };
+        if ICON_THEME_SETTINGS.contains(&(namespace.as_str(), key.as_str()))
+            && IconResolver::refresh_theme()
+        {
+            // the cache no longer matches the theme, so this rescans everything
+            handle_changes(app_handle, Vec::new());
+        }
+    }
+    Ok(())
+}
+
+pub fn start_watching(app_handle: AppHandle) -> Result<(), AppError> {
+    let app_handle_clone = app_handle.clone();
+    let app_dirs = DesktopFileManager::get_app_directories();
+    let app_dirs_clone = app_dirs.clone();
+
+    let debouncer = new_debouncer(
+        Duration::from_secs(1),
+        None,
+        move |result: DebounceEventResult| match result {
+            Ok(events) => {
+                let mut paths: HashSet<PathBuf> = events
+                    .iter()
+                    .flat_map(|event| event.event.paths.iter())
+                    // ancestors of missing app directories report unrelated changes too
+                    .filter(|path| app_dirs_clone.iter().any(|dir| path.starts_with(dir)))
+                    .filter(|path| {
+                        path.is_dir() || path.extension().map_or(false, |ext| ext == "desktop")
+                    })
+                    .cloned()
+                    .collect();
+
+                if let Some(watcher) = app_handle_clone.try_state::<AppDirectoryWatcher>() {
+                    paths.extend(watcher.watches.lock().unwrap().sync());
+                }
+
+                if !paths.is_empty() {
+                    handle_changes(&app_handle_clone, paths.into_iter().collect());
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("app directory watch error: {:?}", error);
+                }
+            }
+        },
+    )
+    .map_err(|e| AppError::DesktopEntry(e.to_string()))?;
+
+    let mut watches = Watches {
+        debouncer,
+        app_dirs,
+        watched: HashSet::new(),
+        ancestors: HashSet::new(),
+    };
+    watches.sync();
+
+    app_handle.manage(AppDirectoryWatcher {
+        watches: Mutex::new(watches),
+    });
+    // may rescan everything, so keep it off the setup thread
+    thread::spawn(AppCache::rely_on_watcher);
+
+    let theme_handle = app_handle.clone();
+    thread::spawn(move || {
+        if let Err(e) = watch_icon_theme(&theme_handle) {
+            eprintln!("Failed to watch the icon theme: {:?}", e);
+        }
+    });
+
+    Ok(())
+}
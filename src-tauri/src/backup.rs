@@ -0,0 +1,978 @@
+use crate::clipboard_history::encryption::{self, decrypt, encrypt};
+use crate::clipboard_history::types::ContentType;
+use crate::error::AppError;
+use crate::frecency::FrecencyManager;
+use crate::migrations::{self, Migration};
+use crate::snippets::manager::SnippetManager;
+use crate::{ai, clipboard_history, frecency, quicklinks, snippets};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Bumped when the archive layout changes in a way older versions can't read.
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SECRETS_ENTRY: &str = "secrets.bin";
+const DATABASES_DIR: &str = "databases";
+const SETTINGS_DIR: &str = "settings";
+const PLUGINS_DIR: &str = "plugins";
+const CLIPBOARD_IMAGES_DIR: &str = "clipboard_images";
+const OAUTH_TOKENS_FILE: &str = "oauth_tokens.json";
+const SETTINGS_FILES: &[&str] = &[
+    "ai_settings.json",
+    "frecency_settings.json",
+    "launch_settings.json",
+    "snippet_settings.json",
+];
+
+// the count isn't stored in backups, so it can't change for real archives;
+// tests use fewer rounds, as 600k take seconds each in debug builds
+const PBKDF2_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+struct TableSpec {
+    name: &'static str,
+    /// Columns identifying the same row in two databases, used when merging.
+    key: &'static [&'static str],
+    /// Whether `id` is an autoincrement column that must not be copied when
+    /// merging, as it would clash with local rows.
+    generated_id: bool,
+}
+
+struct DatabaseSpec {
+    file: &'static str,
+    label: &'static str,
+    migrations: &'static [Migration],
+    tables: &'static [TableSpec],
+    /// Rows are encrypted with the clipboard history key, so they are only
+    /// useful together with the secrets.
+    needs_clipboard_key: bool,
+}
+
+// the file search index is left out, it is rebuilt from disk
+const DATABASES: &[DatabaseSpec] = &[
+    DatabaseSpec {
+        file: "quicklinks.sqlite",
+        label: "quicklinks",
+        migrations: quicklinks::MIGRATIONS,
+        tables: &[TableSpec {
+            name: "quicklinks",
+            key: &["name", "link"],
+            generated_id: true,
+        }],
+        needs_clipboard_key: false,
+    },
+    DatabaseSpec {
+        file: "snippets.sqlite",
+        label: "snippets",
+        migrations: snippets::manager::MIGRATIONS,
+        tables: &[TableSpec {
+            name: "snippets",
+            key: &["keyword"],
+            generated_id: true,
+        }],
+        needs_clipboard_key: false,
+    },
+    DatabaseSpec {
+        file: "frecency.sqlite",
+        label: "ranking data",
+        migrations: frecency::MIGRATIONS,
+        tables: &[
+            TableSpec {
+                name: "frecency",
+                key: &["item_id"],
+                generated_id: false,
+            },
+            TableSpec {
+                name: "hidden_items",
+                key: &["item_id"],
+                generated_id: false,
+            },
+            TableSpec {
+                name: "query_frecency",
+                key: &["query", "item_id"],
+                generated_id: false,
+            },
+        ],
+        needs_clipboard_key: false,
+    },
+    DatabaseSpec {
+        file: "ai_usage.sqlite",
+        label: "AI usage history",
+        migrations: ai::MIGRATIONS,
+        tables: &[TableSpec {
+            name: "ai_generations",
+            key: &["id"],
+            generated_id: false,
+        }],
+        needs_clipboard_key: false,
+    },
+    DatabaseSpec {
+        file: "clipboard_history.sqlite",
+        label: "clipboard history",
+        migrations: clipboard_history::manager::MIGRATIONS,
+        tables: &[TableSpec {
+            name: "clipboard_history",
+            key: &["hash"],
+            generated_id: true,
+        }],
+        needs_clipboard_key: true,
+    },
+];
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format_version: u32,
+    app_version: String,
+    created_at: i64,
+    includes_secrets: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Secrets {
+    oauth_tokens: Option<Value>,
+    clipboard_key: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreMode {
+    /// Local data is discarded in favour of the backup.
+    Replace,
+    /// Backed up entries are added where no local entry exists.
+    Merge,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub restored: Vec<String>,
+    /// Parts of the backup that were left out, with the reason.
+    pub skipped: Vec<String>,
+}
+
+/// A file deleted when it goes out of scope.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn temp_path(data_dir: &Path) -> TempFile {
+    TempFile(data_dir.join(format!(".backup-{}.sqlite", uuid::Uuid::new_v4())))
+}
+
+fn zip_error(error: ZipError) -> AppError {
+    AppError::Backup(error.to_string())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// Encrypts with a key derived from the passphrase. The output is the salt,
+/// then the nonce, then the ciphertext.
+fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce_bytes: [u8; NONCE_LEN] = rand::random();
+    let key = derive_key(passphrase, &salt);
+    let cipher = Aes256Gcm::new((&key).into());
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|e| AppError::Backup(e.to_string()))?;
+
+    let mut sealed = salt.to_vec();
+    sealed.extend_from_slice(&nonce_bytes);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn unseal(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < SALT_LEN + NONCE_LEN {
+        return Err(AppError::Backup("Encrypted secrets are damaged".into()));
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = derive_key(passphrase, salt);
+    let cipher = Aes256Gcm::new((&key).into());
+    cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| AppError::Backup("Wrong passphrase or damaged backup".into()))
+}
+
+fn add_entry(zip: &mut ZipWriter<File>, name: &str, bytes: &[u8]) -> Result<(), AppError> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    zip.write_all(bytes)?;
+    Ok(())
+}
+
+fn add_dir(zip: &mut ZipWriter<File>, dir: &Path, prefix: &str) -> Result<(), AppError> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry.map_err(|e| AppError::Backup(e.to_string()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(dir) else {
+            continue;
+        };
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        add_entry(
+            zip,
+            &format!("{}/{}", prefix, name),
+            &fs::read(entry.path())?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Takes a consistent copy of a live database without stopping its manager.
+fn snapshot_database(db_path: &Path, data_dir: &Path) -> Result<Vec<u8>, AppError> {
+    let db = Connection::open(db_path)?;
+    db.busy_timeout(Duration::from_secs(5))?;
+    let snapshot = temp_path(data_dir);
+    db.execute("VACUUM INTO ?", [snapshot.0.to_string_lossy().as_ref()])?;
+    Ok(fs::read(&snapshot.0)?)
+}
+
+fn write_archive(
+    app: &AppHandle,
+    path: &Path,
+    include_secrets: bool,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| AppError::DirectoryNotFound)?;
+    let passphrase = match (include_secrets, passphrase) {
+        (false, _) => None,
+        (true, Some(passphrase)) if !passphrase.is_empty() => Some(passphrase),
+        (true, _) => {
+            return Err(AppError::Backup(
+                "A passphrase is required to back up secrets".into(),
+            ))
+        }
+    };
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        includes_secrets: passphrase.is_some(),
+    };
+    let manifest =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| AppError::Serialization(e.to_string()))?;
+    add_entry(&mut zip, MANIFEST_ENTRY, &manifest)?;
+
+    for database in DATABASES {
+        if database.needs_clipboard_key && passphrase.is_none() {
+            continue;
+        }
+        let db_path = data_dir.join(database.file);
+        if !db_path.exists() {
+            continue;
+        }
+        let snapshot = snapshot_database(&db_path, &data_dir)?;
+        add_entry(
+            &mut zip,
+            &format!("{}/{}", DATABASES_DIR, database.file),
+            &snapshot,
+        )?;
+    }
+
+    for file in SETTINGS_FILES {
+        let settings_path = data_dir.join(file);
+        if settings_path.exists() {
+            add_entry(
+                &mut zip,
+                &format!("{}/{}", SETTINGS_DIR, file),
+                &fs::read(settings_path)?,
+            )?;
+        }
+    }
+
+    add_dir(&mut zip, &data_dir.join(PLUGINS_DIR), PLUGINS_DIR)?;
+
+    if let Some(passphrase) = passphrase {
+        add_dir(
+            &mut zip,
+            &data_dir.join(CLIPBOARD_IMAGES_DIR),
+            CLIPBOARD_IMAGES_DIR,
+        )?;
+
+        let oauth_path = data_dir.join(OAUTH_TOKENS_FILE);
+        let oauth_tokens = if oauth_path.exists() {
+            serde_json::from_str(&fs::read_to_string(oauth_path)?).ok()
+        } else {
+            None
+        };
+        let secrets = Secrets {
+            oauth_tokens,
+            clipboard_key: Some(hex::encode(encryption::get_encryption_key()?)),
+        };
+        let secrets =
+            serde_json::to_vec(&secrets).map_err(|e| AppError::Serialization(e.to_string()))?;
+        add_entry(&mut zip, SECRETS_ENTRY, &seal(&secrets, passphrase)?)?;
+    }
+
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<Vec<u8>>, AppError> {
+    match archive.by_name(name) {
+        Ok(mut entry) => {
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            Ok(Some(bytes))
+        }
+        Err(ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(zip_error(e)),
+    }
+}
+
+/// Combines two JSON documents. Merging keeps every local top-level key and
+/// only adds the ones missing locally.
+fn combine_json(local: Option<Value>, incoming: Value, mode: RestoreMode) -> Value {
+    match (mode, local) {
+        (RestoreMode::Merge, Some(Value::Object(mut local))) => {
+            if let Value::Object(incoming) = incoming {
+                for (key, value) in incoming {
+                    local.entry(key).or_insert(value);
+                }
+            }
+            Value::Object(local)
+        }
+        (RestoreMode::Merge, Some(local)) => local,
+        _ => incoming,
+    }
+}
+
+fn restore_json(path: &Path, incoming: Value, mode: RestoreMode) -> Result<(), AppError> {
+    let local = if path.exists() {
+        serde_json::from_str(&fs::read_to_string(path)?).ok()
+    } else {
+        None
+    };
+    let combined = combine_json(local, incoming, mode);
+    let content = serde_json::to_string_pretty(&combined)
+        .map_err(|e| AppError::Serialization(e.to_string()))?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn table_columns(db: &Connection, schema: &str, table: &str) -> Result<Vec<String>, AppError> {
+    let mut stmt = db.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+    let columns = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(columns)
+}
+
+fn restore_table(db: &Connection, table: &TableSpec, mode: RestoreMode) -> Result<(), AppError> {
+    let backup_columns = table_columns(db, "backup", table.name)?;
+    let columns: Vec<String> = table_columns(db, "main", table.name)?
+        .into_iter()
+        .filter(|column| backup_columns.contains(column))
+        .filter(|column| !(mode == RestoreMode::Merge && table.generated_id && column == "id"))
+        .collect();
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let column_list = columns.join(", ");
+
+    match mode {
+        RestoreMode::Replace => {
+            db.execute(&format!("DELETE FROM main.{}", table.name), [])?;
+            db.execute(
+                &format!(
+                    "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM backup.{table}",
+                    table = table.name,
+                    columns = column_list
+                ),
+                [],
+            )?;
+        }
+        RestoreMode::Merge => {
+            let matches = table
+                .key
+                .iter()
+                .map(|key| format!("m.{key} = b.{key}"))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            let selected = columns
+                .iter()
+                .map(|column| format!("b.{}", column))
+                .collect::<Vec<_>>()
+                .join(", ");
+            db.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO main.{table} ({columns})
+                     SELECT {selected} FROM backup.{table} AS b
+                     WHERE NOT EXISTS (SELECT 1 FROM main.{table} AS m WHERE {matches})",
+                    table = table.name,
+                    columns = column_list,
+                ),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-encrypts clipboard rows from the backup's key to the local one, so the
+/// local key never changes, and points image items at the local image folder.
+/// Rows that don't decrypt are dropped.
+fn rekey_clipboard_history(
+    db: &Connection,
+    from: &[u8; 32],
+    to: &[u8; 32],
+    image_dir: &Path,
+) -> Result<(), AppError> {
+    let rows = {
+        let mut stmt = db.prepare(
+            "SELECT id, content_type, encrypted_content, encrypted_preview FROM clipboard_history",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (id, content_type, encrypted_content, encrypted_preview) in rows {
+        let Ok(mut content) = decrypt(&encrypted_content, from) else {
+            db.execute("DELETE FROM clipboard_history WHERE id = ?1", [id])?;
+            continue;
+        };
+        if content_type == ContentType::Image.as_str() {
+            if let Some(file_name) = Path::new(&content).file_name() {
+                content = image_dir.join(file_name).to_string_lossy().into_owned();
+            }
+        }
+        let preview = encrypted_preview
+            .and_then(|preview| decrypt(&preview, from).ok())
+            .map(|preview| encrypt(&preview, to))
+            .transpose()?;
+        db.execute(
+            "UPDATE clipboard_history SET encrypted_content = ?1, encrypted_preview = ?2 WHERE id = ?3",
+            params![encrypt(&content, to)?, preview, id],
+        )?;
+    }
+    Ok(())
+}
+
+fn restore_database(
+    database: &DatabaseSpec,
+    bytes: &[u8],
+    data_dir: &Path,
+    mode: RestoreMode,
+    backup_key: Option<&[u8; 32]>,
+) -> Result<(), AppError> {
+    let extracted = temp_path(data_dir);
+    fs::write(&extracted.0, bytes)?;
+    {
+        let mut backup = Connection::open(&extracted.0)?;
+        // bring an older backup up to the schema this version writes
+        migrations::run_without_backup(&mut backup, database.migrations)?;
+        if let Some(backup_key) = backup_key {
+            rekey_clipboard_history(
+                &backup,
+                backup_key,
+                &encryption::get_encryption_key()?,
+                &data_dir.join(CLIPBOARD_IMAGES_DIR),
+            )?;
+        }
+    }
+
+    let mut db = Connection::open(data_dir.join(database.file))?;
+    db.busy_timeout(Duration::from_secs(5))?;
+    migrations::run(&mut db, database.migrations)?;
+    db.execute(
+        "ATTACH DATABASE ?1 AS backup",
+        [extracted.0.to_string_lossy().as_ref()],
+    )?;
+    let result = (|| {
+        let tx = db.transaction()?;
+        for table in database.tables {
+            restore_table(&tx, table, mode)?;
+        }
+        tx.commit()?;
+        Ok(())
+    })();
+    db.execute("DETACH DATABASE backup", [])?;
+    result
+}
+
+/// Extracts the entries under `prefix` into `target`. Replacing clears
+/// `target` first; merging skips top-level entries that already exist.
+fn restore_dir(
+    archive: &mut ZipArchive<File>,
+    prefix: &str,
+    target: &Path,
+    mode: RestoreMode,
+) -> Result<bool, AppError> {
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(zip_error)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(prefix) else {
+            continue;
+        };
+        if entry.is_file()
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            entries.push((index, relative.to_path_buf()));
+        }
+    }
+    if entries.is_empty() {
+        return Ok(false);
+    }
+
+    if mode == RestoreMode::Replace && target.exists() {
+        fs::remove_dir_all(target)?;
+    }
+    let existing: Vec<PathBuf> = match fs::read_dir(target) {
+        Ok(dir) => dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().into())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    for (index, relative) in entries {
+        let Some(top_level) = relative.components().next() else {
+            continue;
+        };
+        if existing
+            .iter()
+            .any(|name| name.as_os_str() == top_level.as_os_str())
+        {
+            continue;
+        }
+        let destination = target.join(&relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut entry = archive.by_index(index).map_err(zip_error)?;
+        let mut file = File::create(destination)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+    Ok(true)
+}
+
+fn restore_archive(
+    app: &AppHandle,
+    path: &Path,
+    mode: RestoreMode,
+    passphrase: Option<&str>,
+) -> Result<RestoreReport, AppError> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| AppError::DirectoryNotFound)?;
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+
+    let manifest: Manifest = read_entry(&mut archive, MANIFEST_ENTRY)?
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| AppError::Backup("Not a launcher backup".into()))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(AppError::Backup(format!(
+            "This backup was made by version {} and uses a newer format",
+            manifest.app_version
+        )));
+    }
+
+    let mut report = RestoreReport::default();
+
+    let secrets = match (read_entry(&mut archive, SECRETS_ENTRY)?, passphrase) {
+        (Some(sealed), Some(passphrase)) if !passphrase.is_empty() => {
+            let secrets = unseal(&sealed, passphrase)?;
+            Some(
+                serde_json::from_slice::<Secrets>(&secrets)
+                    .map_err(|e| AppError::Serialization(e.to_string()))?,
+            )
+        }
+        (Some(_), _) => {
+            report
+                .skipped
+                .push("secrets and clipboard history (no passphrase given)".into());
+            None
+        }
+        (None, _) => None,
+    };
+    let backup_key: Option<[u8; 32]> = secrets
+        .as_ref()
+        .and_then(|secrets| secrets.clipboard_key.as_deref())
+        .and_then(|key| hex::decode(key).ok())
+        .and_then(|key| key.try_into().ok());
+
+    for database in DATABASES {
+        let Some(bytes) = read_entry(
+            &mut archive,
+            &format!("{}/{}", DATABASES_DIR, database.file),
+        )?
+        else {
+            continue;
+        };
+        let key = if database.needs_clipboard_key {
+            match &backup_key {
+                Some(key) => Some(key),
+                None => continue,
+            }
+        } else {
+            None
+        };
+        restore_database(database, &bytes, &data_dir, mode, key)?;
+        report.restored.push(database.label.to_string());
+    }
+
+    for file in SETTINGS_FILES {
+        let Some(bytes) = read_entry(&mut archive, &format!("{}/{}", SETTINGS_DIR, file))? else {
+            continue;
+        };
+        let incoming =
+            serde_json::from_slice(&bytes).map_err(|e| AppError::Serialization(e.to_string()))?;
+        restore_json(&data_dir.join(file), incoming, mode)?;
+        report.restored.push(file.to_string());
+    }
+    app.state::<FrecencyManager>().reload_settings()?;
+    app.state::<SnippetManager>().reload_settings()?;
+    app.state::<SnippetManager>().reload_keywords()?;
+
+    if restore_dir(&mut archive, PLUGINS_DIR, &data_dir.join(PLUGINS_DIR), mode)? {
+        report.restored.push("plugins".into());
+    }
+
+    if let Some(secrets) = secrets {
+        if backup_key.is_some() {
+            // images are shared by content hash, so existing ones are kept
+            restore_dir(
+                &mut archive,
+                CLIPBOARD_IMAGES_DIR,
+                &data_dir.join(CLIPBOARD_IMAGES_DIR),
+                RestoreMode::Merge,
+            )?;
+        }
+        if let Some(oauth_tokens) = secrets.oauth_tokens {
+            restore_json(&data_dir.join(OAUTH_TOKENS_FILE), oauth_tokens, mode)?;
+            report.restored.push("OAuth tokens".into());
+        }
+    }
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn export_backup(
+    app: AppHandle,
+    path: String,
+    include_secrets: bool,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        write_archive(
+            &app,
+            Path::new(&path),
+            include_secrets,
+            passphrase.as_deref(),
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn import_backup(
+    app: AppHandle,
+    path: String,
+    mode: RestoreMode,
+    passphrase: Option<String>,
+) -> Result<RestoreReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        restore_archive(&app, Path::new(&path), mode, passphrase.as_deref())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory removed when the test ends.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "raycast-linux-backup-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const ITEMS: TableSpec = TableSpec {
+        name: "items",
+        key: &["name"],
+        generated_id: true,
+    };
+
+    /// A database with an attached `backup` schema, both holding `items`.
+    fn attached_databases() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute("ATTACH DATABASE ':memory:' AS backup", [])
+            .unwrap();
+        for schema in ["main", "backup"] {
+            db.execute(
+                &format!(
+                    "CREATE TABLE {}.items (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        name TEXT NOT NULL UNIQUE,
+                        value TEXT
+                    )",
+                    schema
+                ),
+                [],
+            )
+            .unwrap();
+        }
+        db.execute_batch(
+            "INSERT INTO main.items (id, name, value) VALUES (1, 'local', 'l'), (2, 'shared', 'local');
+             INSERT INTO backup.items (id, name, value) VALUES (1, 'shared', 'backup'), (7, 'new', 'n');",
+        )
+        .unwrap();
+        db
+    }
+
+    fn items(db: &Connection) -> Vec<(i64, String, String)> {
+        let mut stmt = db
+            .prepare("SELECT id, name, value FROM main.items ORDER BY name")
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn sealed_secrets_need_the_passphrase() {
+        let sealed = seal(b"secret", "correct horse").unwrap();
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        assert_eq!(unseal(&sealed, "correct horse").unwrap(), b"secret");
+        assert!(matches!(
+            unseal(&sealed, "wrong horse"),
+            Err(AppError::Backup(_))
+        ));
+        assert!(unseal(&sealed[..SALT_LEN], "correct horse").is_err());
+    }
+
+    #[test]
+    fn replacing_a_table_copies_the_backup_verbatim() {
+        let db = attached_databases();
+        restore_table(&db, &ITEMS, RestoreMode::Replace).unwrap();
+        assert_eq!(
+            items(&db),
+            vec![
+                (7, "new".to_string(), "n".to_string()),
+                (1, "shared".to_string(), "backup".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merging_a_table_keeps_local_rows_and_generates_new_ids() {
+        let db = attached_databases();
+        restore_table(&db, &ITEMS, RestoreMode::Merge).unwrap();
+        assert_eq!(
+            items(&db),
+            vec![
+                (1, "local".to_string(), "l".to_string()),
+                // the backup's id 7 isn't copied
+                (3, "new".to_string(), "n".to_string()),
+                (2, "shared".to_string(), "local".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merging_copies_ids_that_are_not_generated() {
+        let db = attached_databases();
+        let table = TableSpec {
+            generated_id: false,
+            ..ITEMS
+        };
+        restore_table(&db, &table, RestoreMode::Merge).unwrap();
+        assert!(items(&db).contains(&(7, "new".to_string(), "n".to_string())));
+    }
+
+    #[test]
+    fn clipboard_history_is_rekeyed_to_the_local_key() {
+        let backup_key = [1u8; 32];
+        let local_key = [2u8; 32];
+        let db = Connection::open_in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE clipboard_history (
+                id INTEGER PRIMARY KEY,
+                content_type TEXT NOT NULL,
+                encrypted_content TEXT NOT NULL,
+                encrypted_preview TEXT
+            )",
+            [],
+        )
+        .unwrap();
+        let rows = [
+            (1, "text", encrypt("hello", &backup_key).unwrap(), None),
+            (
+                2,
+                "image",
+                encrypt("/old/home/clipboard_images/abc.png", &backup_key).unwrap(),
+                Some(encrypt("preview", &backup_key).unwrap()),
+            ),
+            // encrypted with some other key, so it can't be kept
+            (3, "text", encrypt("lost", &[3u8; 32]).unwrap(), None),
+        ];
+        for (id, content_type, content, preview) in &rows {
+            db.execute(
+                "INSERT INTO clipboard_history VALUES (?1, ?2, ?3, ?4)",
+                params![id, content_type, content, preview],
+            )
+            .unwrap();
+        }
+
+        let image_dir = Path::new("/data/clipboard_images");
+        rekey_clipboard_history(&db, &backup_key, &local_key, image_dir).unwrap();
+
+        let mut stmt = db
+            .prepare(
+                "SELECT id, encrypted_content, encrypted_preview FROM clipboard_history ORDER BY id",
+            )
+            .unwrap();
+        let rows: Vec<(i64, String, Option<String>)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(decrypt(&rows[0].1, &local_key).unwrap(), "hello");
+        assert!(decrypt(&rows[0].1, &backup_key).is_err());
+        assert_eq!(
+            decrypt(&rows[1].1, &local_key).unwrap(),
+            "/data/clipboard_images/abc.png"
+        );
+        assert_eq!(
+            decrypt(rows[1].2.as_deref().unwrap(), &local_key).unwrap(),
+            "preview"
+        );
+    }
+
+    fn archive(dir: &TestDir, entries: &[(&str, &str)]) -> ZipArchive<File> {
+        let path = dir.0.join("backup.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in entries {
+            add_entry(&mut zip, name, content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        ZipArchive::new(File::open(&path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn restoring_a_dir_ignores_entries_outside_it() {
+        let dir = TestDir::new("traversal");
+        let mut archive = archive(
+            &dir,
+            &[
+                ("plugins/../escaped.txt", "no"),
+                ("plugins/ext/../../escaped.txt", "no"),
+                ("/plugins/absolute.txt", "no"),
+                ("plugins/ext/package.json", "{}"),
+            ],
+        );
+        let target = dir.0.join("data").join("plugins");
+
+        assert!(restore_dir(&mut archive, PLUGINS_DIR, &target, RestoreMode::Replace).unwrap());
+        assert_eq!(
+            fs::read_to_string(target.join("ext/package.json")).unwrap(),
+            "{}"
+        );
+        assert!(!dir.0.join("escaped.txt").exists());
+        assert!(!dir.0.join("data").join("escaped.txt").exists());
+        assert!(!target.join("absolute.txt").exists());
+    }
+
+    #[test]
+    fn merging_a_dir_keeps_existing_top_level_entries() {
+        let dir = TestDir::new("merge-dir");
+        let mut archive = archive(
+            &dir,
+            &[
+                ("plugins/kept/index.js", "backup"),
+                ("plugins/added/index.js", "backup"),
+            ],
+        );
+        let target = dir.0.join("plugins");
+        fs::create_dir_all(target.join("kept")).unwrap();
+        fs::write(target.join("kept/index.js"), "local").unwrap();
+
+        assert!(restore_dir(&mut archive, PLUGINS_DIR, &target, RestoreMode::Merge).unwrap());
+        assert_eq!(
+            fs::read_to_string(target.join("kept/index.js")).unwrap(),
+            "local"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("added/index.js")).unwrap(),
+            "backup"
+        );
+        assert!(!restore_dir(&mut archive, "missing", &target, RestoreMode::Merge).unwrap());
+    }
+}
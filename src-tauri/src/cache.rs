@@ -1,18 +1,39 @@
-use crate::{app::App, desktop::DesktopFileManager, error::AppError};
+use crate::{app::App, desktop::DesktopFileManager, error::AppError, icons::IconResolver};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env, fs,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::SystemTime,
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct AppCache {
     apps: Vec<App>,
-    dir_mod_times: HashMap<PathBuf, SystemTime>,
+    mod_times: HashMap<PathBuf, SystemTime>,
+    locale: Option<String>,
+    desktops: Vec<String>,
+    icon_theme: String,
+    icon_paths: HashMap<String, String>,
 }
 
+// icons are resolved at the size closest to this, in pixels
+const ICON_SIZE: u32 = 64;
+
+// building a resolver indexes every icon theme, so the one from the last scan
+// is kept for incremental updates, along with the theme it was built for
+static ICON_RESOLVER: Lazy<Mutex<Option<(String, Arc<IconResolver>)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+// once the app directories are watched, changes arrive through `apply_changes`
+// and the desktop files no longer have to be checked on every read
+static WATCHED: AtomicBool = AtomicBool::new(false);
+
 impl AppCache {
     pub fn get_cache_path() -> Result<PathBuf, AppError> {
         let cache_dir = env::var("XDG_CACHE_HOME")
@@ -38,18 +59,31 @@ impl AppCache {
         Ok(())
     }
 
+    fn matches_environment(&self) -> bool {
+        self.locale == DesktopFileManager::current_locale()
+            && self.desktops == DesktopFileManager::current_desktops()
+            && self.icon_theme == IconResolver::current_theme()
+    }
+
     pub fn is_stale(&self) -> bool {
-        DesktopFileManager::get_app_directories()
-            .into_iter()
-            .any(|dir| {
-                let current_mod_time = fs::metadata(&dir).ok().and_then(|m| m.modified().ok());
-                let cached_mod_time = self.dir_mod_times.get(&dir);
-
-                match (current_mod_time, cached_mod_time) {
-                    (Some(current), Some(cached)) => current > *cached,
-                    _ => true,
-                }
-            })
+        if !self.matches_environment() {
+            return true;
+        }
+        if WATCHED.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let app_dirs = DesktopFileManager::get_app_directories();
+        DesktopFileManager::get_modification_times(&app_dirs) != self.mod_times
+    }
+
+    /// Called once the app directory watcher is running. The desktop files are
+    /// checked one last time for changes made while the launcher wasn't.
+    pub fn rely_on_watcher() {
+        if let Err(e) = Self::get_apps() {
+            eprintln!("Failed to update app cache: {:?}", e);
+        }
+        WATCHED.store(true, Ordering::Release);
     }
 
     pub fn get_apps() -> Result<Vec<App>, AppError> {
@@ -65,11 +99,24 @@ impl AppCache {
     }
 
     pub fn refresh_and_get_apps() -> Result<Vec<App>, AppError> {
-        let (apps, dir_mod_times) = DesktopFileManager::scan_and_parse_apps()?;
+        let icon_theme = IconResolver::current_theme();
+        let icon_resolver = Arc::new(IconResolver::new(&icon_theme, ICON_SIZE));
+        if let Ok(previous) = Self::get_cache_path().and_then(|p| Self::read_from_file(&p)) {
+            if previous.icon_theme == icon_theme {
+                icon_resolver.seed(&previous.icon_paths);
+            }
+        }
+
+        let (apps, mod_times) = DesktopFileManager::scan_and_parse_apps(&icon_resolver)?;
         let cache_data = AppCache {
             apps: apps.clone(),
-            dir_mod_times,
+            mod_times,
+            locale: DesktopFileManager::current_locale(),
+            desktops: DesktopFileManager::current_desktops(),
+            icon_theme,
+            icon_paths: icon_resolver.resolved_paths(),
         };
+        *ICON_RESOLVER.lock().unwrap() = Some((cache_data.icon_theme.clone(), icon_resolver));
 
         if let Ok(cache_path) = Self::get_cache_path() {
             if let Err(e) = cache_data.write_to_file(&cache_path) {
@@ -80,9 +127,88 @@ impl AppCache {
         Ok(apps)
     }
 
-    pub fn refresh_background() {
-        if let Err(e) = Self::refresh_and_get_apps() {
-            eprintln!("Error refreshing app cache in background: {:?}", e);
+    /// Re-parses only the desktop files at `changed_paths` and updates the cache.
+    /// Falls back to a full rescan when a directory changed or the cache can't
+    /// be reused.
+    pub fn apply_changes(changed_paths: &[PathBuf]) -> Result<Vec<App>, AppError> {
+        let cache_path = Self::get_cache_path()?;
+        let mut cache = match Self::read_from_file(&cache_path) {
+            Ok(cache) if cache.matches_environment() => cache,
+            _ => return Self::refresh_and_get_apps(),
+        };
+
+        let app_dirs = DesktopFileManager::get_app_directories();
+        let mut changed_ids = HashSet::new();
+        for path in changed_paths {
+            if path.extension().map_or(true, |ext| ext != "desktop") {
+                return Self::refresh_and_get_apps();
+            }
+            if let Some(id) = app_dirs
+                .iter()
+                .find_map(|dir| DesktopFileManager::desktop_file_id(dir, path))
+            {
+                changed_ids.insert(id);
+            }
+        }
+
+        let parse_changed = |icon_resolver: &IconResolver| -> Vec<App> {
+            DesktopFileManager::collect_desktop_files(&app_dirs)
+                .iter()
+                .filter(|(id, _)| changed_ids.contains(id))
+                .filter_map(|(id, file_path)| {
+                    DesktopFileManager::parse_desktop_file(id, file_path, icon_resolver)
+                })
+                .collect()
+        };
+        // the last scan's resolver can only miss icons installed since, e.g.
+        // along with the changed apps
+        let reused = Self::cached_icon_resolver(&cache.icon_theme)
+            .map(|resolver| {
+                let apps = parse_changed(&resolver);
+                (resolver, apps)
+            })
+            .filter(|(resolver, _)| !resolver.has_misses());
+        let (icon_resolver, changed_apps) = match reused {
+            Some(reused) => reused,
+            None => {
+                let resolver = Self::new_icon_resolver(&cache);
+                let apps = parse_changed(&resolver);
+                (resolver, apps)
+            }
+        };
+
+        // the file now in effect for an ID may live in another directory, e.g.
+        // when a user override is deleted and the system entry shows through
+        cache.apps.retain(|app| {
+            app.desktop_file_id
+                .as_ref()
+                .map_or(true, |id| !changed_ids.contains(id))
+        });
+        cache.apps.extend(changed_apps);
+        DesktopFileManager::sort_apps(&mut cache.apps);
+
+        cache.mod_times = DesktopFileManager::get_modification_times(&app_dirs);
+        cache.icon_paths.extend(icon_resolver.resolved_paths());
+        cache.write_to_file(&cache_path)?;
+
+        Ok(cache.apps)
+    }
+
+    /// The resolver of the last scan, if it was built for `theme`.
+    fn cached_icon_resolver(theme: &str) -> Option<Arc<IconResolver>> {
+        let cached = ICON_RESOLVER.lock().unwrap();
+        let (cached_theme, resolver) = cached.as_ref()?;
+        if cached_theme != theme {
+            return None;
         }
+        resolver.forget_misses();
+        Some(resolver.clone())
+    }
+
+    fn new_icon_resolver(cache: &AppCache) -> Arc<IconResolver> {
+        let resolver = Arc::new(IconResolver::new(&cache.icon_theme, ICON_SIZE));
+        resolver.seed(&cache.icon_paths);
+        *ICON_RESOLVER.lock().unwrap() = Some((cache.icon_theme.clone(), resolver.clone()));
+        resolver
     }
 }
@@ -4,6 +4,7 @@ use super::{
     types::{ClipboardItem, ContentType, INLINE_CONTENT_THRESHOLD_BYTES, PREVIEW_LENGTH_CHARS},
 };
 use crate::error::AppError;
+use crate::migrations::{self, Migration};
 use chrono::Utc;
 use once_cell::sync::Lazy;
 use rusqlite::{params, Connection, OptionalExtension, Result as RusqliteResult};
@@ -12,6 +13,23 @@ use std::sync::atomic::AtomicBool;
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager};
 
+pub(crate) const MIGRATIONS: &[Migration] = &[Migration::sql(
+    "create clipboard history table",
+    "CREATE TABLE IF NOT EXISTS clipboard_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        hash TEXT UNIQUE NOT NULL,
+        content_type TEXT NOT NULL,
+        encrypted_content TEXT NOT NULL,
+        encrypted_preview TEXT,
+        content_size_bytes INTEGER,
+        source_app_name TEXT,
+        first_copied_at INTEGER NOT NULL,
+        last_copied_at INTEGER NOT NULL,
+        times_copied INTEGER NOT NULL DEFAULT 1,
+        is_pinned INTEGER NOT NULL DEFAULT 0
+    )",
+)];
+
 pub struct ClipboardHistoryManager {
     db: Mutex<Connection>,
     key: [u8; 32],
@@ -64,25 +82,9 @@ impl ClipboardHistoryManager {
         })
     }
 
-    fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS clipboard_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                hash TEXT UNIQUE NOT NULL,
-                content_type TEXT NOT NULL,
-                encrypted_content TEXT NOT NULL,
-                encrypted_preview TEXT,
-                content_size_bytes INTEGER,
-                source_app_name TEXT,
-                first_copied_at INTEGER NOT NULL,
-                last_copied_at INTEGER NOT NULL,
-                times_copied INTEGER NOT NULL DEFAULT 1,
-                is_pinned INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
-        Ok(())
+    fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)
     }
 
     pub fn add_item(
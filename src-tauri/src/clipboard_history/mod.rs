@@ -1,7 +1,7 @@
-mod encryption;
+pub(crate) mod encryption;
 pub mod manager;
 mod monitor;
-mod types;
+pub(crate) mod types;
 
 pub use manager::init;
 use manager::MANAGER;
@@ -1,5 +1,10 @@
-use crate::{app::App, error::AppError};
+use crate::{
+    app::{App, AppAction},
+    error::AppError,
+    icons::IconResolver,
+};
 use freedesktop_file_parser::{parse, EntryType};
+use once_cell::sync::Lazy;
 use rayon::prelude::*;
 use std::{
     collections::{HashMap, HashSet},
@@ -7,18 +12,70 @@ use std::{
     path::{Path, PathBuf},
     time::SystemTime,
 };
+use url::Url;
+
+static LOCALE_KEYS: Lazy<Vec<String>> = Lazy::new(|| {
+    DesktopFileManager::current_locale()
+        .map(|locale| DesktopFileManager::locale_keys(&locale))
+        .unwrap_or_default()
+});
 
 pub struct DesktopFileManager;
 
 impl DesktopFileManager {
+    /// Returns the `share` directories to search, in XDG precedence order
+    /// (`XDG_DATA_HOME` first), followed by Flatpak and Nix profile exports that
+    /// are not always listed in `XDG_DATA_DIRS`.
+    pub fn get_data_directories() -> Vec<PathBuf> {
+        let home_dir = env::var("HOME").ok().map(PathBuf::from);
+
+        let data_home = env::var("XDG_DATA_HOME")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir.as_ref().map(|home| home.join(".local/share")));
+
+        let data_dirs = env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+        let mut dirs: Vec<PathBuf> = data_home.into_iter().collect();
+        dirs.extend(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        );
+
+        if let Some(home) = &home_dir {
+            dirs.push(home.join(".local/share/flatpak/exports/share"));
+            dirs.push(home.join(".nix-profile/share"));
+        }
+        dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+        if let Ok(user) = env::var("USER") {
+            dirs.push(PathBuf::from(format!(
+                "/etc/profiles/per-user/{}/share",
+                user
+            )));
+        }
+        dirs.push(PathBuf::from("/run/current-system/sw/share"));
+
+        let mut seen = HashSet::new();
+        dirs.retain(|dir| seen.insert(dir.clone()));
+        dirs
+    }
+
     pub fn get_app_directories() -> Vec<PathBuf> {
-        let mut app_dirs = vec![
-            PathBuf::from("/usr/share/applications"),
-            PathBuf::from("/usr/local/share/applications"),
-        ];
+        let mut app_dirs: Vec<PathBuf> = Self::get_data_directories()
+            .into_iter()
+            .map(|dir| dir.join("applications"))
+            .collect();
 
-        if let Ok(home_dir) = env::var("HOME") {
-            app_dirs.push(PathBuf::from(home_dir).join(".local/share/applications"));
+        // snap exports desktop files outside of any share directory
+        let snap_dir = PathBuf::from("/var/lib/snapd/desktop/applications");
+        if !app_dirs.contains(&snap_dir) {
+            app_dirs.push(snap_dir);
         }
         app_dirs
     }
@@ -38,80 +95,665 @@ impl DesktopFileManager {
         desktop_files
     }
 
-    pub fn scan_and_parse_apps() -> Result<(Vec<App>, HashMap<PathBuf, SystemTime>), AppError> {
-        let app_dirs = Self::get_app_directories();
-        let desktop_files: Vec<PathBuf> = app_dirs
-            .iter()
-            .filter(|dir| dir.exists())
-            .flat_map(|dir| Self::find_desktop_files(dir))
+    /// Computes the desktop-file ID of `file_path`: its path relative to the
+    /// `applications` directory with `/` replaced by `-`.
+    pub fn desktop_file_id(app_dir: &Path, file_path: &Path) -> Option<String> {
+        let relative = file_path.strip_prefix(app_dir).ok()?;
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
             .collect();
+        Some(components.join("-"))
+    }
+
+    /// Collects the desktop files that are in effect, keyed by desktop-file ID.
+    /// Directories are visited in precedence order, so a file in the user's data
+    /// directory shadows a system one with the same ID.
+    pub fn collect_desktop_files(app_dirs: &[PathBuf]) -> Vec<(String, PathBuf)> {
+        let mut seen_ids = HashSet::new();
+        let mut desktop_files = Vec::new();
 
-        let apps: Vec<App> = desktop_files
+        for dir in app_dirs.iter().filter(|dir| dir.exists()) {
+            let mut files = Self::find_desktop_files(dir);
+            files.sort();
+            for file_path in files {
+                if let Some(id) = Self::desktop_file_id(dir, &file_path) {
+                    if seen_ids.insert(id.clone()) {
+                        desktop_files.push((id, file_path));
+                    }
+                }
+            }
+        }
+        desktop_files
+    }
+
+    pub fn scan_and_parse_apps(
+        icon_resolver: &IconResolver,
+    ) -> Result<(Vec<App>, HashMap<PathBuf, SystemTime>), AppError> {
+        let app_dirs = Self::get_app_directories();
+        let desktop_files = Self::collect_desktop_files(&app_dirs);
+
+        let mut apps: Vec<App> = desktop_files
             .par_iter()
-            .filter_map(|file_path| Self::parse_desktop_file(file_path))
+            .filter_map(|(id, file_path)| Self::parse_desktop_file(id, file_path, icon_resolver))
             .collect();
 
-        let unique_apps = Self::deduplicate_and_sort_apps(apps);
+        Self::sort_apps(&mut apps);
+
+        let mod_times = Self::get_modification_times(&app_dirs);
+
+        Ok((apps, mod_times))
+    }
+
+    pub fn sort_apps(apps: &mut [App]) {
+        apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    }
+
+    /// Returns the current message locale, following the `LC_ALL`,
+    /// `LC_MESSAGES`, `LANG` precedence. `C` and `POSIX` mean untranslated.
+    pub fn current_locale() -> Option<String> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .filter(|value| value != "C" && value != "POSIX")
+    }
+
+    /// Expands a locale such as `sr_YU.UTF-8@Latn` into the keys a localized
+    /// value is looked up under, most specific first.
+    fn locale_keys(locale: &str) -> Vec<String> {
+        let (locale, modifier) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier)),
+            None => (locale, None),
+        };
+        let locale = locale.split('.').next().unwrap_or(locale);
+        let (lang, country) = match locale.split_once('_') {
+            Some((lang, country)) => (lang, Some(country)),
+            None => (locale, None),
+        };
+
+        let mut keys = Vec::new();
+        if let (Some(country), Some(modifier)) = (country, modifier) {
+            keys.push(format!("{}_{}@{}", lang, country, modifier));
+        }
+        if let Some(country) = country {
+            keys.push(format!("{}_{}", lang, country));
+        }
+        if let Some(modifier) = modifier {
+            keys.push(format!("{}@{}", lang, modifier));
+        }
+        keys.push(lang.to_string());
+        keys
+    }
+
+    fn localized<'a, T>(default: &'a T, variants: &'a HashMap<String, T>) -> &'a T {
+        LOCALE_KEYS
+            .iter()
+            .find_map(|key| variants.get(key))
+            .unwrap_or(default)
+    }
+
+    /// Returns the desktops named in `XDG_CURRENT_DESKTOP`.
+    pub fn current_desktops() -> Vec<String> {
+        env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|desktop| !desktop.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn is_shown_in_current_desktop(
+        only_show_in: Option<&[String]>,
+        not_show_in: Option<&[String]>,
+    ) -> bool {
+        let current = Self::current_desktops();
+
+        if let Some(only) = only_show_in {
+            if !current.iter().any(|desktop| only.contains(desktop)) {
+                return false;
+            }
+        }
+        if let Some(not) = not_show_in {
+            if current.iter().any(|desktop| not.contains(desktop)) {
+                return false;
+            }
+        }
+        true
+    }
 
-        let dir_mod_times = Self::get_directory_modification_times(app_dirs)?;
+    /// Checks that a `TryExec` program exists, either as an absolute path or
+    /// somewhere on `PATH`.
+    pub fn is_executable_available(program: &str) -> bool {
+        let program_path = Path::new(program);
+        if program_path.is_absolute() {
+            return program_path.is_file();
+        }
 
-        Ok((unique_apps, dir_mod_times))
+        env::var_os("PATH")
+            .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+            .unwrap_or(false)
     }
 
-    fn parse_desktop_file(file_path: &Path) -> Option<App> {
+    pub fn parse_desktop_file(
+        id: &str,
+        file_path: &Path,
+        icon_resolver: &IconResolver,
+    ) -> Option<App> {
         let content = fs::read_to_string(file_path).ok()?;
         let desktop_file = parse(&content).ok()?;
+        let entry = desktop_file.entry;
 
-        if desktop_file.entry.hidden.unwrap_or(false)
-            || desktop_file.entry.no_display.unwrap_or(false)
-        {
+        if entry.hidden.unwrap_or(false) || entry.no_display.unwrap_or(false) {
             return None;
         }
 
-        if let EntryType::Application(app_fields) = desktop_file.entry.entry_type {
-            if app_fields.exec.is_some() && !desktop_file.entry.name.default.is_empty() {
-                return Some(
-                    App::new(desktop_file.entry.name.default)
-                        .with_comment(desktop_file.entry.comment.map(|lc| lc.default))
-                        .with_exec(app_fields.exec)
-                        .with_icon_path(
-                            desktop_file
-                                .entry
-                                .icon
-                                .and_then(|ic| ic.get_icon_path())
-                                .and_then(|p| p.to_str().map(String::from)),
-                        ),
-                );
+        if !Self::is_shown_in_current_desktop(
+            entry.only_show_in.as_deref(),
+            entry.not_show_in.as_deref(),
+        ) {
+            return None;
+        }
+
+        let EntryType::Application(app_fields) = entry.entry_type else {
+            return None;
+        };
+        if app_fields.exec.is_none() || entry.name.default.is_empty() {
+            return None;
+        }
+        if let Some(try_exec) = &app_fields.try_exec {
+            if !Self::is_executable_available(try_exec) {
+                return None;
             }
         }
-        None
+
+        let actions = app_fields
+            .actions
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|action_id| desktop_file.actions.get(action_id))
+            .filter(|action| !action.name.default.is_empty())
+            .map(|action| AppAction {
+                id: action.ref_name.clone(),
+                name: Self::localized(&action.name.default, &action.name.variants).clone(),
+                exec: action.exec.clone(),
+                icon_path: action
+                    .icon
+                    .as_ref()
+                    .and_then(|ic| icon_resolver.resolve(&ic.content)),
+            })
+            .collect();
+
+        // keep the untranslated keywords too, so English searches still match
+        let keywords = app_fields
+            .keywords
+            .map(|kw| {
+                let mut keywords = Self::localized(&kw.default, &kw.variants).clone();
+                for keyword in kw.default {
+                    if !keywords.contains(&keyword) {
+                        keywords.push(keyword);
+                    }
+                }
+                keywords
+            })
+            .unwrap_or_default();
+
+        let icon = entry.icon.map(|ic| ic.content).filter(|ic| !ic.is_empty());
+
+        Some(
+            App::new(Self::localized(&entry.name.default, &entry.name.variants).clone())
+                .with_comment(
+                    entry
+                        .comment
+                        .map(|lc| Self::localized(&lc.default, &lc.variants).clone()),
+                )
+                .with_generic_name(
+                    entry
+                        .generic_name
+                        .map(|lc| Self::localized(&lc.default, &lc.variants).clone()),
+                )
+                .with_keywords(keywords)
+                .with_categories(app_fields.categories.unwrap_or_default())
+                .with_mime_types(app_fields.mime_type.unwrap_or_default())
+                .with_exec(app_fields.exec)
+                .with_icon_path(icon.as_deref().and_then(|ic| icon_resolver.resolve(ic)))
+                .with_icon(icon)
+                .with_terminal(app_fields.terminal.unwrap_or(false))
+                .with_working_directory(app_fields.path)
+                .with_startup_wm_class(app_fields.startup_wm_class)
+                .with_desktop_file_id(Some(id.to_string()))
+                .with_desktop_file_path(file_path.to_str().map(String::from))
+                .with_actions(actions),
+        )
     }
 
-    fn deduplicate_and_sort_apps(apps: Vec<App>) -> Vec<App> {
-        let mut unique_apps = Vec::new();
-        let mut seen_app_names = HashSet::new();
+    /// Splits an `Exec` value into arguments following the quoting rules of the
+    /// Desktop Entry spec. Each argument is paired with whether it was quoted,
+    /// because field codes are not expanded inside quoted arguments.
+    pub fn split_exec(exec: &str) -> Result<Vec<(String, bool)>, AppError> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut in_arg = false;
+        let mut in_quotes = false;
+        let mut arg_quoted = false;
+        let mut chars = exec.chars();
 
-        for app in apps {
-            if seen_app_names.insert(app.name.clone()) {
-                unique_apps.push(app);
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' => in_quotes = false,
+                    '\\' => match chars.next() {
+                        Some(next @ ('"' | '`' | '$' | '\\')) => current.push(next),
+                        Some(next) => {
+                            current.push('\\');
+                            current.push(next);
+                        }
+                        None => {
+                            return Err(AppError::DesktopEntry(format!(
+                                "Dangling escape in Exec: {}",
+                                exec
+                            )))
+                        }
+                    },
+                    _ => current.push(c),
+                }
+                continue;
+            }
+
+            match c {
+                '"' => {
+                    in_quotes = true;
+                    in_arg = true;
+                    arg_quoted = true;
+                }
+                ' ' | '\t' | '\n' => {
+                    if in_arg {
+                        args.push((std::mem::take(&mut current), arg_quoted));
+                        in_arg = false;
+                        arg_quoted = false;
+                    }
+                }
+                _ => {
+                    current.push(c);
+                    in_arg = true;
+                }
             }
         }
 
-        unique_apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-        unique_apps
+        if in_quotes {
+            return Err(AppError::DesktopEntry(format!(
+                "Unterminated quote in Exec: {}",
+                exec
+            )));
+        }
+        if in_arg {
+            args.push((current, arg_quoted));
+        }
+        Ok(args)
     }
 
-    fn get_directory_modification_times(
-        app_dirs: Vec<PathBuf>,
-    ) -> Result<HashMap<PathBuf, SystemTime>, AppError> {
-        Ok(app_dirs
-            .into_iter()
-            .filter_map(|dir| {
-                fs::metadata(&dir)
-                    .and_then(|m| m.modified())
+    /// Converts a launch target into a local path for `%f`/`%F`. Non-`file://`
+    /// URLs cannot be passed to these field codes and are skipped.
+    fn target_as_file(target: &str) -> Option<String> {
+        if let Ok(url) = Url::parse(target) {
+            if url.scheme() == "file" {
+                return url
+                    .to_file_path()
                     .ok()
-                    .map(|mod_time| (dir, mod_time))
-            })
-            .collect())
+                    .map(|p| p.to_string_lossy().into_owned());
+            }
+            // single-letter schemes are windows drive letters, not URLs
+            if url.scheme().len() > 1 {
+                return None;
+            }
+        }
+        Some(target.to_string())
+    }
+
+    fn expand_field_codes(
+        args: &[(String, bool)],
+        app: &App,
+        targets: &[String],
+    ) -> Result<Vec<String>, AppError> {
+        let mut expanded = Vec::new();
+
+        for (arg, quoted) in args {
+            if *quoted {
+                expanded.push(arg.clone());
+                continue;
+            }
+
+            match arg.as_str() {
+                "%F" => {
+                    expanded.extend(targets.iter().filter_map(|t| Self::target_as_file(t)));
+                    continue;
+                }
+                "%U" => {
+                    expanded.extend(targets.iter().cloned());
+                    continue;
+                }
+                "%i" => {
+                    if let Some(icon) = app.icon.as_deref().filter(|icon| !icon.is_empty()) {
+                        expanded.push("--icon".to_string());
+                        expanded.push(icon.to_string());
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mut value = String::with_capacity(arg.len());
+            let mut dropped_code = false;
+            let mut chars = arg.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    value.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('%') => value.push('%'),
+                    Some('f') => match targets.first().and_then(|t| Self::target_as_file(t)) {
+                        Some(file) => value.push_str(&file),
+                        None => dropped_code = true,
+                    },
+                    Some('u') => match targets.first() {
+                        Some(url) => value.push_str(url),
+                        None => dropped_code = true,
+                    },
+                    Some('c') => value.push_str(&app.name),
+                    Some('k') => match &app.desktop_file_path {
+                        Some(path) => value.push_str(path),
+                        None => dropped_code = true,
+                    },
+                    // deprecated field codes, and %i outside of its own argument, are removed
+                    Some('d' | 'D' | 'n' | 'N' | 'v' | 'm' | 'i') => dropped_code = true,
+                    Some(other) => {
+                        return Err(AppError::DesktopEntry(format!(
+                            "Invalid field code '%{}' in Exec",
+                            other
+                        )))
+                    }
+                    None => {
+                        return Err(AppError::DesktopEntry(
+                            "Exec ends with an incomplete field code".to_string(),
+                        ))
+                    }
+                }
+            }
+
+            if !(value.is_empty() && dropped_code) {
+                expanded.push(value);
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    fn has_field_code(arg: &str, codes: &[char]) -> bool {
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c == '%' {
+                match chars.next() {
+                    Some(code) if codes.contains(&code) => return true,
+                    _ => {}
+                }
+            }
+        }
+        false
+    }
+
+    /// Builds the command lines needed to launch `app` with `targets` (files or
+    /// URLs). Apps that only take a single `%f`/`%u` get one command per target.
+    pub fn build_exec_commands(
+        exec: &str,
+        app: &App,
+        targets: &[String],
+    ) -> Result<Vec<Vec<String>>, AppError> {
+        let args = Self::split_exec(exec)?;
+        if args.is_empty() {
+            return Err(AppError::DesktopEntry("Empty exec command".to_string()));
+        }
+
+        let takes_list = args
+            .iter()
+            .any(|(arg, quoted)| !quoted && (arg == "%F" || arg == "%U"));
+        let takes_single = args
+            .iter()
+            .any(|(arg, quoted)| !quoted && Self::has_field_code(arg, &['f', 'u']));
+
+        let commands = if targets.len() > 1 && takes_single && !takes_list {
+            targets
+                .iter()
+                .map(|target| Self::expand_field_codes(&args, app, std::slice::from_ref(target)))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![Self::expand_field_codes(&args, app, targets)?]
+        };
+
+        if commands
+            .iter()
+            .any(|command| command.first().map_or(true, |program| program.is_empty()))
+        {
+            return Err(AppError::DesktopEntry(format!(
+                "Exec expanded to an empty command: {}",
+                exec
+            )));
+        }
+        Ok(commands)
+    }
+
+    /// Records the modification time of every app directory, nested directory
+    /// and desktop file, so that added, removed and edited entries are all noticed.
+    pub fn get_modification_times(app_dirs: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+        fn visit(path: &Path, mod_times: &mut HashMap<PathBuf, SystemTime>) {
+            if let Ok(mod_time) = fs::metadata(path).and_then(|m| m.modified()) {
+                mod_times.insert(path.to_path_buf(), mod_time);
+            }
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_dir() || path.extension().map_or(false, |ext| ext == "desktop") {
+                        visit(&path, mod_times);
+                    }
+                }
+            }
+        }
+
+        let mut mod_times = HashMap::new();
+        for dir in app_dirs.iter().filter(|dir| dir.exists()) {
+            visit(dir, &mut mod_times);
+        }
+        mod_times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(exec: &str) -> Vec<(String, bool)> {
+        DesktopFileManager::split_exec(exec).unwrap()
+    }
+
+    fn app() -> App {
+        App::new("Viewer".to_string())
+            .with_icon(Some("viewer".to_string()))
+            .with_icon_path(Some("/icons/viewer.png".to_string()))
+            .with_desktop_file_path(Some("/apps/viewer.desktop".to_string()))
+    }
+
+    fn commands(exec: &str, targets: &[&str]) -> Vec<Vec<String>> {
+        let targets: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+        DesktopFileManager::build_exec_commands(exec, &app(), &targets).unwrap()
+    }
+
+    fn expand(exec: &str, targets: &[&str]) -> Vec<String> {
+        let targets: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+        DesktopFileManager::expand_field_codes(&split(exec), &app(), &targets).unwrap()
+    }
+
+    #[test]
+    fn split_exec_separates_on_whitespace() {
+        assert_eq!(
+            split("viewer  --new-window\t-x"),
+            vec![
+                ("viewer".to_string(), false),
+                ("--new-window".to_string(), false),
+                ("-x".to_string(), false),
+            ]
+        );
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn split_exec_unescapes_quoted_arguments() {
+        assert_eq!(
+            split(r#"sh -c "echo \"hi\" \`date\` \$HOME \\ done""#),
+            vec![
+                ("sh".to_string(), false),
+                ("-c".to_string(), false),
+                (r#"echo "hi" `date` $HOME \ done"#.to_string(), true),
+            ]
+        );
+        // other escapes are kept as they are
+        assert_eq!(split(r#""a\nb""#), vec![(r"a\nb".to_string(), true)]);
+        assert_eq!(split(r#""""#), vec![(String::new(), true)]);
+        assert_eq!(
+            split(r#"--name="My App""#),
+            vec![("--name=My App".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn split_exec_rejects_unterminated_quotes() {
+        assert!(matches!(
+            DesktopFileManager::split_exec(r#"viewer "unterminated"#),
+            Err(AppError::DesktopEntry(_))
+        ));
+        assert!(matches!(
+            DesktopFileManager::split_exec(r#"viewer "dangling\"#),
+            Err(AppError::DesktopEntry(_))
+        ));
+    }
+
+    #[test]
+    fn percent_signs_are_escaped_by_doubling() {
+        assert_eq!(
+            expand("viewer --zoom=100%%", &[]),
+            vec!["viewer", "--zoom=100%"]
+        );
+    }
+
+    #[test]
+    fn quoted_arguments_are_not_expanded() {
+        assert_eq!(
+            expand(r#"viewer "%f" "%%""#, &["/tmp/a"]),
+            vec!["viewer", "%f", "%%"]
+        );
+    }
+
+    #[test]
+    fn single_file_codes() {
+        assert_eq!(commands("viewer %f", &[]), vec![vec!["viewer"]]);
+        assert_eq!(
+            commands("viewer %f", &["file:///tmp/a%20b"]),
+            vec![vec!["viewer", "/tmp/a b"]]
+        );
+        assert_eq!(
+            commands("viewer --open=%f", &["/tmp/a", "/tmp/b"]),
+            vec![
+                vec!["viewer", "--open=/tmp/a"],
+                vec!["viewer", "--open=/tmp/b"]
+            ]
+        );
+        // %f only takes local files
+        assert_eq!(
+            commands("viewer %f", &["https://example.com"]),
+            vec![vec!["viewer"]]
+        );
+    }
+
+    #[test]
+    fn file_list_codes() {
+        assert_eq!(commands("viewer %F", &[]), vec![vec!["viewer"]]);
+        assert_eq!(
+            commands("viewer %F", &["/tmp/a"]),
+            vec![vec!["viewer", "/tmp/a"]]
+        );
+        assert_eq!(
+            commands(
+                "viewer %F",
+                &["/tmp/a", "file:///tmp/b", "https://example.com"]
+            ),
+            vec![vec!["viewer", "/tmp/a", "/tmp/b"]]
+        );
+    }
+
+    #[test]
+    fn url_codes() {
+        assert_eq!(commands("browser %u", &[]), vec![vec!["browser"]]);
+        assert_eq!(
+            commands("browser %u", &["https://example.com"]),
+            vec![vec!["browser", "https://example.com"]]
+        );
+        assert_eq!(
+            commands("browser %u", &["https://a.example", "https://b.example"]),
+            vec![
+                vec!["browser", "https://a.example"],
+                vec!["browser", "https://b.example"],
+            ]
+        );
+        assert_eq!(commands("browser %U", &[]), vec![vec!["browser"]]);
+        assert_eq!(
+            commands("browser %U", &["https://a.example", "/tmp/b"]),
+            vec![vec!["browser", "https://a.example", "/tmp/b"]]
+        );
+    }
+
+    #[test]
+    fn icon_name_and_location_codes() {
+        assert_eq!(
+            expand("viewer %i --class=%c %k", &[]),
+            vec![
+                "viewer",
+                "--icon",
+                "viewer",
+                "--class=Viewer",
+                "/apps/viewer.desktop",
+            ]
+        );
+        let targets: Vec<String> = Vec::new();
+        let no_icon = App::new("Viewer".to_string())
+            .with_icon(Some(String::new()))
+            .with_icon_path(Some("/icons/viewer.png".to_string()));
+        assert_eq!(
+            DesktopFileManager::expand_field_codes(&split("viewer %i %k"), &no_icon, &targets)
+                .unwrap(),
+            vec!["viewer"]
+        );
+    }
+
+    #[test]
+    fn deprecated_codes_are_dropped() {
+        assert_eq!(
+            expand("viewer %d %D %n %N %v %m --x", &["/tmp/a"]),
+            vec!["viewer", "--x"]
+        );
+        assert_eq!(expand("viewer --dir=%d", &[]), vec!["viewer", "--dir="]);
+    }
+
+    #[test]
+    fn invalid_exec_lines_are_rejected() {
+        let targets: Vec<String> = Vec::new();
+        for exec in ["viewer %z", "viewer %", "", "%f"] {
+            assert!(
+                matches!(
+                    DesktopFileManager::build_exec_commands(exec, &app(), &targets),
+                    Err(AppError::DesktopEntry(_))
+                ),
+                "{:?} was accepted",
+                exec
+            );
+        }
     }
 }
@@ -11,6 +11,12 @@ pub enum AppError {
     Frecency(String),
     FileSearch(String),
     Ai(String),
+    DesktopEntry(String),
+    Migration(String),
+    Backup(String),
+    Quicklink(String),
+    Favicon(String),
+    WindowManager(String),
 }
 
 impl From<io::Error> for AppError {
@@ -55,6 +61,12 @@ impl std::fmt::Display for AppError {
             AppError::Frecency(msg) => write!(f, "Frecency error: {}", msg),
             AppError::FileSearch(msg) => write!(f, "File search error: {}", msg),
             AppError::Ai(msg) => write!(f, "AI error: {}", msg),
+            AppError::DesktopEntry(msg) => write!(f, "Desktop entry error: {}", msg),
+            AppError::Migration(msg) => write!(f, "Migration error: {}", msg),
+            AppError::Backup(msg) => write!(f, "Backup error: {}", msg),
+            AppError::Quicklink(msg) => write!(f, "Quicklink error: {}", msg),
+            AppError::Favicon(msg) => write!(f, "Favicon error: {}", msg),
+            AppError::WindowManager(msg) => write!(f, "Window manager error: {}", msg),
         }
     }
 }
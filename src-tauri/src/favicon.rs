@@ -0,0 +1,566 @@
+use crate::error::AppError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+// pages are only read for their <link> tags, which live in <head>
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+const MAX_ICON_BYTES: usize = 1024 * 1024;
+/// Once the cache grows past this, the least recently written icons go.
+const MAX_CACHE_BYTES: u64 = 20 * 1024 * 1024;
+
+static LINK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\b(?P<key>rel|href)\s*=\s*(?:"(?P<dq>[^"]*)"|'(?P<sq>[^']*)'|(?P<uq>[^\s>]+))"#,
+    )
+    .unwrap()
+});
+
+/// Downloads site icons into a size-capped directory, one file per host.
+#[derive(Clone)]
+pub struct FaviconCache {
+    dir: PathBuf,
+    client: reqwest::Client,
+}
+
+/// The result of storing an icon.
+pub struct CachedFavicon {
+    pub path: PathBuf,
+    /// Icons removed to stay under the cap. Anything pointing at them has to
+    /// forget them.
+    pub evicted: Vec<PathBuf>,
+}
+
+impl FaviconCache {
+    pub fn new(dir: PathBuf) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .user_agent(concat!("raycast-linux/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| AppError::Favicon(e.to_string()))?;
+        Ok(Self { dir, client })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the icon for the site `page_url` is on. A cached icon is
+    /// reused unless `refresh` is set.
+    pub async fn fetch(&self, page_url: &str, refresh: bool) -> Result<CachedFavicon, AppError> {
+        let page_url = Url::parse(page_url).map_err(|e| AppError::Favicon(e.to_string()))?;
+        if !matches!(page_url.scheme(), "http" | "https") {
+            return Err(AppError::Favicon(format!(
+                "{} is not a web address",
+                page_url
+            )));
+        }
+        let host = page_url
+            .host_str()
+            .ok_or_else(|| AppError::Favicon(format!("{} has no host", page_url)))?;
+        let stem = hex::encode(Sha256::digest(host.as_bytes()));
+
+        if !refresh {
+            if let Some(path) = self.find_cached(&stem) {
+                return Ok(CachedFavicon {
+                    path,
+                    evicted: Vec::new(),
+                });
+            }
+        }
+
+        let mut last_error = AppError::Favicon(format!("No icon found for {}", host));
+        for candidate in self.candidates(&page_url).await {
+            match self.download(&candidate).await {
+                Ok((bytes, extension)) => {
+                    return self.store(&stem, extension, &bytes);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn find_cached(&self, stem: &str) -> Option<PathBuf> {
+        fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.file_stem().is_some_and(|s| s == stem))
+    }
+
+    /// Icons declared by the page, larger touch icons first, then the
+    /// conventional `/favicon.ico`.
+    async fn candidates(&self, page_url: &Url) -> Vec<Url> {
+        let mut touch_icons = Vec::new();
+        let mut icons = Vec::new();
+        let mut base = page_url.clone();
+
+        if let Ok(response) = self.client.get(page_url.clone()).send().await {
+            // redirects move relative hrefs along with them
+            base = response.url().clone();
+            if let Ok(body) = read_prefix(response, MAX_PAGE_BYTES).await {
+                let html = String::from_utf8_lossy(&body);
+                for tag in LINK_TAG_REGEX.find_iter(&html) {
+                    let (mut rel, mut href) = (None, None);
+                    for cap in ATTRIBUTE_REGEX.captures_iter(tag.as_str()) {
+                        let value = cap
+                            .name("dq")
+                            .or_else(|| cap.name("sq"))
+                            .or_else(|| cap.name("uq"))
+                            .map(|m| m.as_str().trim().to_string());
+                        match cap["key"].to_ascii_lowercase().as_str() {
+                            "rel" => rel = value.map(|v| v.to_ascii_lowercase()),
+                            _ => href = value,
+                        }
+                    }
+                    let (Some(rel), Some(href)) = (rel, href) else {
+                        continue;
+                    };
+                    let Ok(url) = base.join(&href) else {
+                        continue;
+                    };
+                    let rels: Vec<&str> = rel.split_whitespace().collect();
+                    if rels.iter().any(|r| r.starts_with("apple-touch-icon")) {
+                        touch_icons.push(url);
+                    } else if rels.contains(&"icon") {
+                        icons.push(url);
+                    }
+                }
+            }
+        }
+
+        let mut candidates = touch_icons;
+        candidates.append(&mut icons);
+        if let Ok(fallback) = base.join("/favicon.ico") {
+            if !candidates.contains(&fallback) {
+                candidates.push(fallback);
+            }
+        }
+        candidates
+    }
+
+    async fn download(&self, url: &Url) -> Result<(Vec<u8>, &'static str), AppError> {
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| AppError::Favicon(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(AppError::Favicon(format!(
+                "{} returned {}",
+                url,
+                response.status()
+            )));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_ascii_lowercase())
+            .unwrap_or_default();
+        let bytes = read_limited(response, MAX_ICON_BYTES).await?;
+        let extension = image_extension(&content_type, &bytes)
+            .ok_or_else(|| AppError::Favicon(format!("{} is not an image", url)))?;
+        Ok((bytes, extension))
+    }
+
+    fn store(&self, stem: &str, extension: &str, bytes: &[u8]) -> Result<CachedFavicon, AppError> {
+        fs::create_dir_all(&self.dir)?;
+        // a refreshed icon may come in a different format
+        if let Some(previous) = self.find_cached(stem) {
+            fs::remove_file(previous)?;
+        }
+        let path = self.dir.join(format!("{}.{}", stem, extension));
+        fs::write(&path, bytes)?;
+        let evicted = self.enforce_cap(&path)?;
+        Ok(CachedFavicon { path, evicted })
+    }
+
+    /// Deletes the oldest icons until the cache fits, never `keep`.
+    fn enforce_cap(&self, keep: &Path) -> Result<Vec<PathBuf>, AppError> {
+        let mut entries: Vec<(PathBuf, u64, SystemTime)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                metadata
+                    .is_file()
+                    .then(|| (entry.path(), metadata.len(), modified))
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut evicted = Vec::new();
+        for (path, size, _) in entries {
+            if total <= MAX_CACHE_BYTES {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+            evicted.push(path);
+        }
+        Ok(evicted)
+    }
+}
+
+/// Reads at most `limit` bytes of the body and drops the rest.
+async fn read_prefix(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>, AppError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::Favicon(e.to_string()))?
+    {
+        let room = limit - body.len();
+        if chunk.len() >= room {
+            body.extend_from_slice(&chunk[..room]);
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Reads the whole body, failing if it is larger than `limit`.
+async fn read_limited(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>, AppError> {
+    let too_large = |url: &Url| AppError::Favicon(format!("{} is too large", url));
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large(response.url()));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::Favicon(e.to_string()))?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large(response.url()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Picks a file extension from the content type, falling back to sniffing
+/// the bytes since servers often send icons as `application/octet-stream`.
+fn image_extension(content_type: &str, bytes: &[u8]) -> Option<&'static str> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let by_type = match content_type.split(';').next().unwrap_or("").trim() {
+        "image/png" => Some("png"),
+        "image/x-icon" | "image/vnd.microsoft.icon" | "image/ico" => Some("ico"),
+        "image/svg+xml" => Some("svg"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    };
+    by_type.or_else(|| {
+        if bytes.starts_with(b"\x89PNG") {
+            Some("png")
+        } else if bytes.starts_with(&[0, 0, 1, 0]) {
+            Some("ico")
+        } else if bytes.starts_with(b"GIF8") {
+            Some("gif")
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some("jpg")
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+            Some("webp")
+        } else if String::from_utf8_lossy(&bytes[..bytes.len().min(512)]).contains("<svg") {
+            Some("svg")
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\nfake";
+    const ICO: &[u8] = &[0, 0, 1, 0, 1, 0];
+
+    struct Reply {
+        status: &'static str,
+        headers: Vec<(&'static str, String)>,
+        body: Vec<u8>,
+        // leaves out Content-Length, so the size is only known while reading
+        stream: bool,
+    }
+
+    fn reply(content_type: &str, body: &[u8]) -> Reply {
+        Reply {
+            status: "200 OK",
+            headers: vec![("Content-Type", content_type.to_string())],
+            body: body.to_vec(),
+            stream: false,
+        }
+    }
+
+    fn redirect(location: &str) -> Reply {
+        Reply {
+            status: "302 Found",
+            headers: vec![("Location", location.to_string())],
+            body: Vec::new(),
+            stream: false,
+        }
+    }
+
+    /// Serves fixed replies by path and 404s everything else. Returns the
+    /// server's base URL.
+    fn serve(routes: Vec<(&'static str, Reply)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let routes: HashMap<_, _> = routes.into_iter().collect();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let target = request.split_whitespace().nth(1).unwrap_or("/");
+                let path = target.split('?').next().unwrap_or(target);
+
+                let not_found = Reply {
+                    status: "404 Not Found",
+                    headers: Vec::new(),
+                    body: Vec::new(),
+                    stream: false,
+                };
+                let reply = routes.get(path).unwrap_or(&not_found);
+                let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\n", reply.status);
+                for (name, value) in &reply.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                if !reply.stream {
+                    head.push_str(&format!("Content-Length: {}\r\n", reply.body.len()));
+                }
+                head.push_str("\r\n");
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&reply.body);
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    /// A scratch directory removed when the test ends.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "raycast-linux-favicon-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    async fn fetch(dir: &TestDir, url: &str) -> Result<Vec<u8>, AppError> {
+        let cache = FaviconCache::new(dir.0.clone()).unwrap();
+        let favicon = cache.fetch(url, false).await?;
+        Ok(fs::read(favicon.path).unwrap())
+    }
+
+    #[tokio::test]
+    async fn touch_icons_come_before_icons() {
+        let base = serve(vec![
+            (
+                "/",
+                reply(
+                    "text/html",
+                    br#"<head>
+                        <link rel="icon" href="/icon.png">
+                        <link rel='apple-touch-icon' sizes=180x180 href='/touch.png'>
+                    </head>"#,
+                ),
+            ),
+            ("/icon.png", reply("image/png", b"\x89PNG icon")),
+            ("/touch.png", reply("image/png", b"\x89PNG touch")),
+            ("/favicon.ico", reply("image/x-icon", ICO)),
+        ]);
+        let dir = TestDir::new("touch");
+        assert_eq!(fetch(&dir, &base).await.unwrap(), b"\x89PNG touch");
+    }
+
+    #[tokio::test]
+    async fn link_icons_come_before_the_fallback() {
+        let base = serve(vec![
+            (
+                "/",
+                reply(
+                    "text/html",
+                    br#"<link href="/icon.png" rel="shortcut icon">"#,
+                ),
+            ),
+            ("/icon.png", reply("image/png", PNG)),
+            ("/favicon.ico", reply("image/x-icon", ICO)),
+        ]);
+        let dir = TestDir::new("link");
+        assert_eq!(fetch(&dir, &base).await.unwrap(), PNG);
+    }
+
+    #[tokio::test]
+    async fn relative_hrefs_resolve_against_the_redirected_page() {
+        let base = serve(vec![
+            ("/", redirect("/docs/index.html")),
+            (
+                "/docs/index.html",
+                reply("text/html", br#"<link rel=icon href=static/icon.png>"#),
+            ),
+            ("/docs/static/icon.png", reply("image/png", PNG)),
+        ]);
+        let dir = TestDir::new("redirect");
+        assert_eq!(fetch(&dir, &format!("{}/?q=1", base)).await.unwrap(), PNG);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_favicon_ico() {
+        let base = serve(vec![
+            ("/", reply("text/html", b"<html><head></head></html>")),
+            ("/favicon.ico", reply("application/octet-stream", ICO)),
+        ]);
+        let dir = TestDir::new("fallback");
+        let cache = FaviconCache::new(dir.0.clone()).unwrap();
+        let favicon = cache.fetch(&base, false).await.unwrap();
+        assert_eq!(favicon.path.extension().unwrap(), "ico");
+        assert_eq!(fs::read(favicon.path).unwrap(), ICO);
+    }
+
+    #[tokio::test]
+    async fn non_images_are_rejected() {
+        let base = serve(vec![
+            (
+                "/",
+                reply("text/html", br#"<link rel="icon" href="/login">"#),
+            ),
+            ("/login", reply("text/html", b"<html>Please sign in</html>")),
+            ("/favicon.ico", reply("text/plain", b"not an icon")),
+        ]);
+        let dir = TestDir::new("non-image");
+        assert!(matches!(
+            fetch(&dir, &base).await,
+            Err(AppError::Favicon(_))
+        ));
+        assert!(!dir.0.exists() || fs::read_dir(&dir.0).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_icons_are_rejected() {
+        let huge = {
+            let mut bytes = PNG.to_vec();
+            bytes.resize(MAX_ICON_BYTES + 1, 0);
+            bytes
+        };
+        let mut streamed = reply("image/png", &huge);
+        streamed.stream = true;
+        let base = serve(vec![
+            (
+                "/",
+                reply("text/html", br#"<link rel="icon" href="/big.png">"#),
+            ),
+            ("/big.png", reply("image/png", &huge)),
+            ("/favicon.ico", streamed),
+        ]);
+        let dir = TestDir::new("oversized");
+        match fetch(&dir, &base).await {
+            Err(AppError::Favicon(message)) => assert!(message.ends_with("is too large")),
+            other => panic!("expected a size error, got {:?}", other.map(|b| b.len())),
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_icons_are_reused_unless_refreshed() {
+        let base = serve(vec![
+            ("/", reply("text/html", b"")),
+            ("/favicon.ico", reply("image/x-icon", ICO)),
+        ]);
+        let dir = TestDir::new("reuse");
+        let cache = FaviconCache::new(dir.0.clone()).unwrap();
+        let first = cache.fetch(&base, false).await.unwrap();
+        fs::write(&first.path, PNG).unwrap();
+
+        let cached = cache.fetch(&base, false).await.unwrap();
+        assert_eq!(fs::read(&cached.path).unwrap(), PNG);
+        let refreshed = cache.fetch(&base, true).await.unwrap();
+        assert_eq!(fs::read(&refreshed.path).unwrap(), ICO);
+    }
+
+    #[test]
+    fn enforce_cap_evicts_the_oldest_icons_but_never_the_kept_one() {
+        let dir = TestDir::new("cap");
+        fs::create_dir_all(&dir.0).unwrap();
+        let cache = FaviconCache::new(dir.0.clone()).unwrap();
+        let size = (MAX_CACHE_BYTES / 3 + 1) as usize;
+        let now = SystemTime::now();
+        let icons: Vec<PathBuf> = (0..4)
+            .map(|age| {
+                let path = dir.0.join(format!("{}.png", age));
+                let file = fs::File::create(&path).unwrap();
+                file.set_len(size as u64).unwrap();
+                file.set_modified(now - Duration::from_secs(60 * (4 - age)))
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        // the oldest icon is the one just stored, so the next two go instead
+        let evicted = cache.enforce_cap(&icons[0]).unwrap();
+        assert_eq!(evicted, vec![icons[1].clone(), icons[2].clone()]);
+        assert!(icons[0].exists());
+        assert!(icons[3].exists());
+        assert!(cache.enforce_cap(&icons[3]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn image_extension_sniffs_untyped_bytes() {
+        assert_eq!(
+            image_extension("image/png; charset=binary", b"x"),
+            Some("png")
+        );
+        assert_eq!(
+            image_extension("application/octet-stream", ICO),
+            Some("ico")
+        );
+        assert_eq!(
+            image_extension("text/plain", b"<?xml?><svg xmlns=\"\"></svg>"),
+            Some("svg")
+        );
+        assert_eq!(image_extension("text/html", b"<html></html>"), None);
+        assert_eq!(image_extension("image/png", b""), None);
+    }
+}
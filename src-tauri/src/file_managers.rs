@@ -0,0 +1,404 @@
+use crate::{cache::AppCache, mime::MimeAssociations, window_manager};
+use std::path::PathBuf;
+use url::Url;
+use zbus::{
+    blocking::{connection, fdo::DBusProxy, Connection, Proxy},
+    zvariant::{OwnedObjectPath, OwnedValue},
+    Message,
+};
+
+const FILE_MANAGER_SERVICE: &str = "org.freedesktop.FileManager1";
+const DOLPHIN_SERVICE_PREFIX: &str = "org.kde.dolphin-";
+
+const ATSPI_REGISTRY: &str = "org.a11y.atspi.Registry";
+const ATSPI_ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const ATSPI_ACCESSIBLE: &str = "org.a11y.atspi.Accessible";
+// values of AtspiRole and AtspiStateType
+const ATSPI_ROLE_FRAME: u32 = 23;
+const ATSPI_VIEW_ROLES: [u32; 4] = [31, 55, 65, 66]; // list, table, tree, tree table
+const ATSPI_STATE_ACTIVE: u32 = 1;
+const ATSPI_STATE_SELECTED: u32 = 23;
+const ATSPI_STATE_SHOWING: u32 = 25;
+// how deep below a window item views are looked for
+const MAX_VIEW_DEPTH: usize = 12;
+
+/// What backends need to talk to a file manager.
+pub struct SelectionContext {
+    connection: Connection,
+}
+
+impl SelectionContext {
+    fn dbus(&self) -> Result<DBusProxy<'_>, String> {
+        DBusProxy::new(&self.connection).map_err(|e| e.to_string())
+    }
+
+    /// The program name of the process that owns a bus name.
+    fn owner_program(&self, service: &str) -> Option<String> {
+        let name = zbus::names::BusName::try_from(service).ok()?;
+        let pid = self
+            .dbus()
+            .ok()?
+            .get_connection_unix_process_id(name)
+            .ok()?;
+        std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|comm| comm.trim().to_string())
+    }
+
+    fn services_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.dbus()
+            .and_then(|dbus| dbus.list_names().map_err(|e| e.to_string()))
+            .map(|names| {
+                names
+                    .into_iter()
+                    .map(|name| name.to_string())
+                    .filter(|name| name.starts_with(prefix))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A file manager the selected files can be read from.
+pub trait FileManagerBackend: Send + Sync {
+    /// The desktop file IDs the file manager is installed under.
+    fn desktop_file_ids(&self) -> &'static [&'static str];
+    fn is_running(&self, context: &SelectionContext) -> bool;
+    /// Returns the files selected in the file manager's frontmost window.
+    fn selected_paths(&self, context: &SelectionContext) -> Result<Vec<PathBuf>, String>;
+}
+
+/// File managers that publish the selection of each window as a
+/// `SelectedUris` property on an `org.<program>.Window` interface next to
+/// `org.freedesktop.FileManager1`.
+struct WindowInterfaceBackend {
+    program: &'static str,
+    desktop_file_ids: &'static [&'static str],
+}
+
+impl FileManagerBackend for WindowInterfaceBackend {
+    fn desktop_file_ids(&self) -> &'static [&'static str] {
+        self.desktop_file_ids
+    }
+
+    fn is_running(&self, context: &SelectionContext) -> bool {
+        context.owner_program(FILE_MANAGER_SERVICE).as_deref() == Some(self.program)
+    }
+
+    fn selected_paths(&self, context: &SelectionContext) -> Result<Vec<PathBuf>, String> {
+        let proxy = Proxy::new(
+            &context.connection,
+            FILE_MANAGER_SERVICE,
+            "/org/freedesktop/FileManager1",
+            FILE_MANAGER_SERVICE,
+        )
+        .map_err(|e| e.to_string())?;
+        let windows: Vec<zbus::zvariant::OwnedObjectPath> =
+            proxy.call("GetWindows", &()).map_err(|e| e.to_string())?;
+
+        let window_interface = format!("org.{}.Window", self.program);
+        for window_path in windows.iter().rev() {
+            let Ok(window) = Proxy::new(
+                &context.connection,
+                FILE_MANAGER_SERVICE,
+                window_path.as_ref(),
+                window_interface.as_str(),
+            ) else {
+                continue;
+            };
+            if window.get_property::<bool>("Active").unwrap_or(false) {
+                let uris: Vec<String> = window
+                    .get_property("SelectedUris")
+                    .map_err(|e| e.to_string())?;
+                return Ok(uris
+                    .iter()
+                    .filter_map(|uri| Url::parse(uri).ok())
+                    .filter_map(|url| url.to_file_path().ok())
+                    .collect());
+            }
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Dolphin has no D-Bus API for its selection, so it is read from the
+/// accessibility tree instead: each view reports its folder as its name or
+/// description, and its items by file name with a selected state.
+struct DolphinBackend;
+
+impl DolphinBackend {
+    /// The Dolphin process whose window is in front. Every instance owns an
+    /// `org.kde.dolphin-<pid>` name and usually has a single window.
+    fn frontmost_pid(context: &SelectionContext) -> Option<u32> {
+        let pids: Vec<u32> = context
+            .services_with_prefix(DOLPHIN_SERVICE_PREFIX)
+            .iter()
+            .filter_map(|service| service[DOLPHIN_SERVICE_PREFIX.len()..].parse().ok())
+            .collect();
+        let frontmost = window_manager::frontmost_window().and_then(|window| window.pid);
+        match frontmost {
+            Some(pid) if pids.contains(&pid) => Some(pid),
+            _ if pids.len() == 1 => Some(pids[0]),
+            _ => None,
+        }
+    }
+
+    /// The window to read from: the active one, or the only one.
+    fn main_window(bus: &AccessibilityBus, app: &AccessibleNode) -> Option<AccessibleNode> {
+        let windows: Vec<AccessibleNode> = bus
+            .children(app)
+            .into_iter()
+            .filter(|window| bus.role(window) == Some(ATSPI_ROLE_FRAME))
+            .collect();
+        if let Some(active) = windows
+            .iter()
+            .find(|window| bus.has_state(window, ATSPI_STATE_ACTIVE))
+        {
+            return Some(active.clone());
+        }
+        match windows.as_slice() {
+            [window] => Some(window.clone()),
+            _ => None,
+        }
+    }
+
+    /// The visible item views of `window`, each with the folder it shows.
+    /// Hidden tabs aren't showing and split views show two.
+    fn views(bus: &AccessibilityBus, window: &AccessibleNode) -> Vec<(AccessibleNode, PathBuf)> {
+        let mut views = Vec::new();
+        let mut pending = vec![(window.clone(), 0)];
+        while let Some((node, depth)) = pending.pop() {
+            if !bus.has_state(&node, ATSPI_STATE_SHOWING) {
+                continue;
+            }
+            if bus
+                .role(&node)
+                .is_some_and(|role| ATSPI_VIEW_ROLES.contains(&role))
+            {
+                let folder = [bus.name(&node), bus.description(&node)]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|text| local_folder(&text));
+                if let Some(folder) = folder {
+                    views.push((node, folder));
+                    continue;
+                }
+            }
+            if depth < MAX_VIEW_DEPTH {
+                pending.extend(
+                    bus.children(&node)
+                        .into_iter()
+                        .map(|child| (child, depth + 1)),
+                );
+            }
+        }
+        views
+    }
+}
+
+/// Reads a folder from a view's accessible text, which Dolphin sets to a
+/// local path or to a URL.
+fn local_folder(text: &str) -> Option<PathBuf> {
+    let path = if text.starts_with('/') {
+        PathBuf::from(text)
+    } else {
+        Url::parse(text).ok()?.to_file_path().ok()?
+    };
+    Some(path).filter(|path| path.is_dir())
+}
+
+impl FileManagerBackend for DolphinBackend {
+    fn desktop_file_ids(&self) -> &'static [&'static str] {
+        &["org.kde.dolphin.desktop"]
+    }
+
+    fn is_running(&self, context: &SelectionContext) -> bool {
+        !context
+            .services_with_prefix(DOLPHIN_SERVICE_PREFIX)
+            .is_empty()
+    }
+
+    fn selected_paths(&self, context: &SelectionContext) -> Result<Vec<PathBuf>, String> {
+        let Some(pid) = Self::frontmost_pid(context) else {
+            return Ok(Vec::new());
+        };
+        let bus = AccessibilityBus::connect(&context.connection)?;
+        let app = bus.application(pid).ok_or_else(|| {
+            "Dolphin isn't exposed to accessibility tools, so its selection can't be read"
+                .to_string()
+        })?;
+        let Some(window) = Self::main_window(&bus, &app) else {
+            return Ok(Vec::new());
+        };
+
+        for (view, folder) in Self::views(&bus, &window) {
+            let paths: Vec<PathBuf> = bus
+                .children(&view)
+                .iter()
+                .filter(|item| bus.has_state(item, ATSPI_STATE_SELECTED))
+                .filter_map(|item| bus.name(item))
+                .map(|name| folder.join(name))
+                // items of some types, e.g. desktop files, show a title instead
+                .filter(|path| path.exists())
+                .collect();
+            if !paths.is_empty() {
+                return Ok(paths);
+            }
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// An object on the accessibility bus: the owning connection and its path.
+type AccessibleNode = (String, OwnedObjectPath);
+
+/// The AT-SPI bus, which is separate from the session bus.
+struct AccessibilityBus {
+    connection: Connection,
+}
+
+impl AccessibilityBus {
+    fn connect(session: &Connection) -> Result<Self, String> {
+        let address: String = session
+            .call_method(
+                Some("org.a11y.Bus"),
+                "/org/a11y/bus",
+                Some("org.a11y.Bus"),
+                "GetAddress",
+                &(),
+            )
+            .and_then(|reply| reply.body().deserialize())
+            .map_err(|e| format!("Failed to find the accessibility bus: {}", e))?;
+        let connection = connection::Builder::address(address.as_str())
+            .and_then(|builder| builder.build())
+            .map_err(|e| format!("Failed to connect to the accessibility bus: {}", e))?;
+        Ok(Self { connection })
+    }
+
+    fn call<B>(
+        &self,
+        node: &AccessibleNode,
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> Option<Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.connection
+            .call_method(
+                Some(node.0.as_str()),
+                node.1.as_str(),
+                Some(interface),
+                method,
+                body,
+            )
+            .ok()
+    }
+
+    fn text_property(&self, node: &AccessibleNode, property: &str) -> Option<String> {
+        let value: OwnedValue = self
+            .call(
+                node,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                &(ATSPI_ACCESSIBLE, property),
+            )?
+            .body()
+            .deserialize()
+            .ok()?;
+        String::try_from(value).ok().filter(|text| !text.is_empty())
+    }
+
+    /// The registered application owned by the process `pid`.
+    fn application(&self, pid: u32) -> Option<AccessibleNode> {
+        let dbus = DBusProxy::new(&self.connection).ok()?;
+        let root = (
+            ATSPI_REGISTRY.to_string(),
+            OwnedObjectPath::try_from(ATSPI_ROOT_PATH).ok()?,
+        );
+        self.children(&root).into_iter().find(|(service, _)| {
+            zbus::names::BusName::try_from(service.as_str())
+                .ok()
+                .and_then(|name| dbus.get_connection_unix_process_id(name).ok())
+                == Some(pid)
+        })
+    }
+
+    fn children(&self, node: &AccessibleNode) -> Vec<AccessibleNode> {
+        self.call(node, ATSPI_ACCESSIBLE, "GetChildren", &())
+            .and_then(|reply| reply.body().deserialize().ok())
+            .unwrap_or_default()
+    }
+
+    fn role(&self, node: &AccessibleNode) -> Option<u32> {
+        self.call(node, ATSPI_ACCESSIBLE, "GetRole", &())?
+            .body()
+            .deserialize()
+            .ok()
+    }
+
+    fn has_state(&self, node: &AccessibleNode, state: u32) -> bool {
+        self.call(node, ATSPI_ACCESSIBLE, "GetState", &())
+            .and_then(|reply| reply.body().deserialize::<Vec<u32>>().ok())
+            .and_then(|words| words.get((state / 32) as usize).copied())
+            .is_some_and(|word| word & (1 << (state % 32)) != 0)
+    }
+
+    fn name(&self, node: &AccessibleNode) -> Option<String> {
+        self.text_property(node, "Name")
+    }
+
+    fn description(&self, node: &AccessibleNode) -> Option<String> {
+        self.text_property(node, "Description")
+    }
+}
+
+fn backends() -> Vec<Box<dyn FileManagerBackend>> {
+    vec![
+        Box::new(WindowInterfaceBackend {
+            program: "nautilus",
+            desktop_file_ids: &["org.gnome.Nautilus.desktop"],
+        }),
+        Box::new(WindowInterfaceBackend {
+            program: "nemo",
+            desktop_file_ids: &["nemo.desktop"],
+        }),
+        Box::new(DolphinBackend),
+    ]
+}
+
+fn default_file_manager() -> Option<String> {
+    let apps = AppCache::get_apps().ok()?;
+    MimeAssociations::new(&apps)
+        .default_app("inode/directory")
+        .and_then(|app| app.desktop_file_id.clone())
+}
+
+/// Reads the selected files from the first running file manager that can
+/// report them, starting with the user's default one. Fails with the first
+/// backend's error when none of the running file managers could.
+pub fn selected_files() -> Result<Vec<PathBuf>, String> {
+    let connection = Connection::session().map_err(|e| e.to_string())?;
+    let context = SelectionContext { connection };
+
+    let mut backends = backends();
+    if let Some(default) = default_file_manager() {
+        backends.sort_by_key(|backend| !backend.desktop_file_ids().contains(&default.as_str()));
+    }
+
+    let mut first_error = None;
+    for backend in backends
+        .iter()
+        .filter(|backend| backend.is_running(&context))
+    {
+        match backend.selected_paths(&context) {
+            Ok(paths) => return Ok(paths),
+            Err(e) => {
+                eprintln!("Failed to read file manager selection: {}", e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(Vec::new()), Err)
+}
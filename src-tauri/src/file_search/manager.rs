@@ -6,6 +6,40 @@ use tauri::{AppHandle, Manager};
 
 use super::types::IndexedFile;
 use crate::error::AppError;
+use crate::migrations::{self, Migration};
+
+const MIGRATIONS: &[Migration] = &[Migration::sql(
+    "create file index",
+    "CREATE TABLE IF NOT EXISTS file_index (
+        path TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        parent_path TEXT NOT NULL,
+        file_type TEXT NOT NULL,
+        last_modified INTEGER NOT NULL
+    );
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS file_index_fts
+    USING fts5(name, content='file_index', content_rowid='rowid', tokenize = 'porter unicode61');
+
+    CREATE TRIGGER IF NOT EXISTS file_index_after_insert
+    AFTER INSERT ON file_index
+    BEGIN
+        INSERT INTO file_index_fts(rowid, name) VALUES (new.rowid, new.name);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS file_index_after_delete
+    AFTER DELETE ON file_index
+    BEGIN
+        INSERT INTO file_index_fts(file_index_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS file_index_after_update
+    AFTER UPDATE ON file_index
+    BEGIN
+        INSERT INTO file_index_fts(file_index_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
+        INSERT INTO file_index_fts(rowid, name) VALUES (new.rowid, new.name);
+    END;",
+)];
 
 #[derive(Clone)]
 pub struct FileSearchManager {
@@ -31,55 +65,9 @@ impl FileSearchManager {
         })
     }
 
-    pub fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS file_index (
-                path TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                parent_path TEXT NOT NULL,
-                file_type TEXT NOT NULL,
-                last_modified INTEGER NOT NULL
-            )",
-            [],
-        )?;
-
-        db.execute(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS file_index_fts
-             USING fts5(name, content='file_index', content_rowid='rowid', tokenize = 'porter unicode61')",
-            [],
-        )?;
-
-        db.execute(
-            "CREATE TRIGGER IF NOT EXISTS file_index_after_insert
-             AFTER INSERT ON file_index
-             BEGIN
-                INSERT INTO file_index_fts(rowid, name) VALUES (new.rowid, new.name);
-             END;",
-            [],
-        )?;
-
-        db.execute(
-            "CREATE TRIGGER IF NOT EXISTS file_index_after_delete
-             AFTER DELETE ON file_index
-             BEGIN
-                INSERT INTO file_index_fts(file_index_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
-             END;",
-            [],
-        )?;
-
-        db.execute(
-            "CREATE TRIGGER IF NOT EXISTS file_index_after_update
-             AFTER UPDATE ON file_index
-             BEGIN
-                INSERT INTO file_index_fts(file_index_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
-                INSERT INTO file_index_fts(rowid, name) VALUES (new.rowid, new.name);
-             END;",
-            [],
-        )?;
-
-        Ok(())
+    pub fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)
     }
 
     pub fn add_file(&self, file: &IndexedFile) -> Result<(), AppError> {
@@ -144,80 +144,16 @@ fn get_selected_finder_items_windows() -> Result<Vec<FileSystemItem>, String> {
 
 #[cfg(target_os = "linux")]
 async fn get_from_file_manager() -> Result<Vec<FileSystemItem>, String> {
-    use url::Url;
-    use zbus;
-
-    let connection = match zbus::Connection::session().await {
-        Ok(c) => c,
-        Err(_) => return Ok(vec![]),
-    };
-
-    let proxy = match zbus::Proxy::new(
-        &connection,
-        "org.freedesktop.FileManager1",
-        "/org/freedesktop/FileManager1",
-        "org.freedesktop.FileManager1",
-    )
-    .await
-    {
-        Ok(p) => p,
-        Err(_) => return Ok(vec![]),
-    };
-
-    let fm_service: String = proxy.destination().to_string();
-    if fm_service.is_empty() {
-        return Ok(vec![]);
-    }
-
-    let fm_name = fm_service.split('.').last().unwrap_or_default();
-    let window_interface = format!("org.{}.Window", fm_name);
-
-    if fm_name != "nautilus" && fm_name != "nemo" {
-        return Ok(vec![]);
-    }
-
-    let response = match proxy.call_method("GetWindows", &()).await {
-        Ok(r) => r,
-        Err(_) => return Ok(vec![]),
-    };
-
-    let body = response.body();
-    let windows: Vec<zbus::zvariant::ObjectPath> = body.deserialize().unwrap_or_default();
-
-    for window_path in windows.iter().rev() {
-        let fm_service_ref = fm_service.as_str();
-        let window_interface_ref = window_interface.as_str();
-        let window_proxy = match zbus::Proxy::new(
-            &connection,
-            fm_service_ref,
-            window_path,
-            window_interface_ref,
-        )
+    let paths = tauri::async_runtime::spawn_blocking(crate::file_managers::selected_files)
         .await
-        {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
-        if let Ok(is_active) = window_proxy.get_property::<bool>("Active").await {
-            if is_active {
-                if let Ok(uris) = window_proxy
-                    .get_property::<Vec<String>>("SelectedUris")
-                    .await
-                {
-                    let paths = uris
-                        .iter()
-                        .filter_map(|uri_str| Url::parse(uri_str).ok())
-                        .filter_map(|url| url.to_file_path().ok())
-                        .map(|path_buf| FileSystemItem {
-                            path: path_buf.to_string_lossy().into_owned(),
-                        })
-                        .collect();
-                    return Ok(paths);
-                }
-            }
-        }
-    }
-    Ok(vec![])
+        .map_err(|e| e.to_string())??;
+
+    Ok(paths
+        .into_iter()
+        .map(|path| FileSystemItem {
+            path: path.to_string_lossy().into_owned(),
+        })
+        .collect())
 }
 
 #[cfg(target_os = "linux")]
@@ -250,9 +186,13 @@ fn get_from_clipboard() -> Result<Vec<FileSystemItem>, String> {
 
 #[cfg(target_os = "linux")]
 async fn get_selected_finder_items_linux() -> Result<Vec<FileSystemItem>, String> {
-    if let Ok(paths) = get_from_file_manager().await {
-        if !paths.is_empty() {
-            return Ok(paths);
+    let mut file_manager_error = None;
+    match get_from_file_manager().await {
+        Ok(paths) if !paths.is_empty() => return Ok(paths),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Failed to query the file manager: {}", e);
+            file_manager_error = Some(e);
         }
     }
 
@@ -262,5 +202,10 @@ async fn get_selected_finder_items_linux() -> Result<Vec<FileSystemItem>, String
         }
     }
 
-    Err("Could not determine selected files. Please copy them to your clipboard.".to_string())
+    Err(match file_manager_error {
+        Some(e) => format!("{}. Please copy the files to your clipboard instead.", e),
+        None => {
+            "Could not determine selected files. Please copy them to your clipboard.".to_string()
+        }
+    })
 }
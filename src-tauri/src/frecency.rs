@@ -1,20 +1,132 @@
 use crate::error::AppError;
+use crate::migrations::{self, Migration};
 use chrono::Utc;
-use rusqlite::{params, Connection, Result as RusqliteResult};
-use serde::Serialize;
+use rusqlite::{params, Connection, OptionalExtension, Result as RusqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager};
 
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration::sql(
+        "create frecency and hidden items tables",
+        "CREATE TABLE IF NOT EXISTS frecency (
+            item_id TEXT PRIMARY KEY,
+            use_count INTEGER NOT NULL DEFAULT 0,
+            last_used_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS hidden_items (item_id TEXT PRIMARY KEY);",
+    ),
+    Migration::code("add decayed score and source", |db| {
+        if migrations::add_column_if_missing(db, "frecency", "score", "REAL NOT NULL DEFAULT 0")? {
+            // every earlier use counts fully as of the last one
+            db.execute("UPDATE frecency SET score = use_count", [])?;
+        }
+        migrations::add_column_if_missing(db, "frecency", "source", "TEXT")?;
+        Ok(())
+    }),
+    Migration::code("record when and why items were hidden", |db| {
+        migrations::add_column_if_missing(
+            db,
+            "hidden_items",
+            "hidden_at",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        migrations::add_column_if_missing(db, "hidden_items", "reason", "TEXT")?;
+        migrations::add_column_if_missing(db, "hidden_items", "source", "TEXT")?;
+        Ok(())
+    }),
+    Migration::sql(
+        "create query frecency table",
+        "CREATE TABLE IF NOT EXISTS query_frecency (
+            query TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            score REAL NOT NULL,
+            last_used_at INTEGER NOT NULL,
+            PRIMARY KEY (query, item_id)
+        )",
+    ),
+];
+
+const DEFAULT_HALF_LIFE_HOURS: f64 = 72.0;
+// query entries decayed below this score are dropped
+const MIN_QUERY_SCORE: f64 = 0.05;
+const MAX_QUERY_ENTRIES: usize = 5000;
+
 #[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct FrecencyData {
     pub item_id: String,
+    pub source: Option<String>,
     pub use_count: i64,
     pub last_used_at: i64,
+    /// Decayed and weighted score at the time of the query.
+    pub score: f64,
+    /// Decayed score from earlier selections of this item for the same or a
+    /// longer query.
+    pub query_score: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HiddenItem {
+    pub item_id: String,
+    pub hidden_at: i64,
+    /// Why the item was hidden, e.g. `user` for the Hide action.
+    pub reason: Option<String>,
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FrecencySettings {
+    /// Time after which a use counts half as much.
+    pub half_life_hours: f64,
+    /// Multipliers for the score of items from a source, e.g. `app` or
+    /// `quicklink`. Sources without an entry are weighted 1.
+    pub source_weights: HashMap<String, f64>,
+}
+
+impl Default for FrecencySettings {
+    fn default() -> Self {
+        Self {
+            half_life_hours: DEFAULT_HALF_LIFE_HOURS,
+            source_weights: HashMap::new(),
+        }
+    }
+}
+
+impl FrecencySettings {
+    fn half_life_seconds(&self) -> f64 {
+        self.half_life_hours.max(f64::EPSILON) * 3600.0
+    }
+
+    fn weight(&self, source: Option<&str>) -> f64 {
+        source
+            .and_then(|source| self.source_weights.get(source))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Decays a score stored at `since` to its value at `now`.
+    fn decay(&self, score: f64, since: i64, now: i64) -> f64 {
+        let elapsed = (now - since).max(0) as f64;
+        score * 0.5f64.powf(elapsed / self.half_life_seconds())
+    }
+}
+
+fn normalize_query(query: Option<&str>) -> Option<String> {
+    query
+        .map(|query| query.trim().to_lowercase())
+        .filter(|query| !query.is_empty())
 }
 
 pub struct FrecencyManager {
     db: Mutex<Connection>,
+    settings_path: PathBuf,
+    settings: Mutex<FrecencySettings>,
 }
 
 impl FrecencyManager {
@@ -25,79 +137,287 @@ impl FrecencyManager {
             .map_err(|_| AppError::DirectoryNotFound)?;
         let db_path = data_dir.join("frecency.sqlite");
         let db = Connection::open(db_path)?;
-        let manager = Self { db: Mutex::new(db) };
+        let settings_path = data_dir.join("frecency_settings.json");
+        let settings = Self::read_settings(&settings_path)?;
+        let manager = Self {
+            db: Mutex::new(db),
+            settings_path,
+            settings: Mutex::new(settings),
+        };
         manager.init_db()?;
         Ok(manager)
     }
 
-    fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS frecency (
-                item_id TEXT PRIMARY KEY,
-                use_count INTEGER NOT NULL DEFAULT 0,
-                last_used_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS hidden_items (item_id TEXT PRIMARY KEY)",
-            [],
-        )?;
+    fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)
+    }
+
+    fn read_settings(path: &Path) -> Result<FrecencySettings, AppError> {
+        if !path.exists() {
+            return Ok(FrecencySettings::default());
+        }
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(FrecencySettings::default());
+        }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
+
+    pub fn get_settings(&self) -> FrecencySettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: FrecencySettings) -> Result<(), AppError> {
+        if settings.half_life_hours.is_nan() || settings.half_life_hours <= 0.0 {
+            return Err(AppError::Frecency(
+                "Half-life must be greater than zero".to_string(),
+            ));
+        }
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(&self.settings_path, content)?;
+        *self.settings.lock().unwrap() = settings;
         Ok(())
     }
 
-    pub fn record_usage(&self, item_id: String) -> Result<(), AppError> {
-        let db = self.db.lock().unwrap();
+    /// Picks up settings written to disk by someone else, e.g. a restore.
+    pub fn reload_settings(&self) -> Result<(), AppError> {
+        let settings = Self::read_settings(&self.settings_path)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    /// Adds one use to an item's score after decaying the score it had
+    /// accumulated until now. When the item was picked from search results,
+    /// `query` is what had been typed, so it ranks higher for that query next
+    /// time.
+    pub fn record_usage(
+        &self,
+        item_id: String,
+        source: Option<String>,
+        query: Option<String>,
+    ) -> Result<(), AppError> {
+        let settings = self.get_settings();
+        let mut db = self.db.lock().unwrap();
         let now = Utc::now().timestamp();
-        db.execute(
-            "INSERT INTO frecency (item_id, use_count, last_used_at) VALUES (?, 1, ?)
+        let tx = db.transaction()?;
+
+        let previous: Option<(f64, i64)> = tx
+            .query_row(
+                "SELECT score, last_used_at FROM frecency WHERE item_id = ?",
+                params![item_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let score = previous
+            .map(|(score, last_used_at)| settings.decay(score, last_used_at, now))
+            .unwrap_or(0.0)
+            + 1.0;
+
+        tx.execute(
+            "INSERT INTO frecency (item_id, use_count, last_used_at, score, source)
+             VALUES (?, 1, ?, ?, ?)
              ON CONFLICT(item_id) DO UPDATE SET
                 use_count = use_count + 1,
-                last_used_at = excluded.last_used_at",
-            params![item_id, now],
+                last_used_at = excluded.last_used_at,
+                score = excluded.score,
+                source = COALESCE(excluded.source, source)",
+            params![item_id, now, score, source],
         )?;
+
+        if let Some(query) = normalize_query(query.as_deref()) {
+            let previous: Option<(f64, i64)> = tx
+                .query_row(
+                    "SELECT score, last_used_at FROM query_frecency
+                     WHERE query = ? AND item_id = ?",
+                    params![query, item_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let score = previous
+                .map(|(score, last_used_at)| settings.decay(score, last_used_at, now))
+                .unwrap_or(0.0)
+                + 1.0;
+
+            tx.execute(
+                "INSERT OR REPLACE INTO query_frecency (query, item_id, score, last_used_at)
+                 VALUES (?, ?, ?, ?)",
+                params![query, item_id, score, now],
+            )?;
+            Self::prune_query_entries(&tx, &settings, now)?;
+        }
+
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn get_frecency_data(&self) -> Result<Vec<FrecencyData>, AppError> {
+    /// Drops query entries that have decayed to almost nothing and keeps only
+    /// the highest scoring ones once there are too many.
+    fn prune_query_entries(
+        db: &Connection,
+        settings: &FrecencySettings,
+        now: i64,
+    ) -> RusqliteResult<()> {
+        let mut stmt = db.prepare("SELECT rowid, score, last_used_at FROM query_frecency")?;
+        let mut entries = stmt
+            .query_map([], |row| {
+                let score: f64 = row.get(1)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    settings.decay(score, row.get(2)?, now),
+                ))
+            })?
+            .collect::<RusqliteResult<Vec<(i64, f64)>>>()?;
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut delete = db.prepare("DELETE FROM query_frecency WHERE rowid = ?")?;
+        for (index, (rowid, score)) in entries.into_iter().enumerate() {
+            if index >= MAX_QUERY_ENTRIES || score < MIN_QUERY_SCORE {
+                delete.execute(params![rowid])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sums the decayed scores of selections made for `query` or for longer
+    /// queries starting with it. Longer queries count in proportion to how
+    /// much of them has been typed.
+    fn query_scores(
+        db: &Connection,
+        settings: &FrecencySettings,
+        query: &str,
+        now: i64,
+    ) -> RusqliteResult<HashMap<String, f64>> {
+        let mut stmt = db.prepare(
+            "SELECT query, item_id, score, last_used_at FROM query_frecency
+             WHERE substr(query, 1, length(?1)) = ?1",
+        )?;
+        let rows = stmt.query_map(params![query], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+
+        let typed = query.chars().count() as f64;
+        let mut scores = HashMap::new();
+        for row in rows {
+            let (stored_query, item_id, score, last_used_at) = row?;
+            let coverage = typed / stored_query.chars().count() as f64;
+            *scores.entry(item_id).or_insert(0.0) +=
+                settings.decay(score, last_used_at, now) * coverage;
+        }
+        Ok(scores)
+    }
+
+    /// Returns the used items that are not hidden, highest score first. With
+    /// a `query`, items picked for it before also carry a query score and are
+    /// ranked by the sum of both.
+    pub fn get_ranked_items(&self, query: Option<String>) -> Result<Vec<FrecencyData>, AppError> {
+        let settings = self.get_settings();
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare("SELECT item_id, use_count, last_used_at FROM frecency")?;
+        let now = Utc::now().timestamp();
+
+        let query_scores = match normalize_query(query.as_deref()) {
+            Some(query) => Self::query_scores(&db, &settings, &query, now)?,
+            None => HashMap::new(),
+        };
+
+        let mut stmt = db.prepare(
+            "SELECT item_id, source, use_count, last_used_at, score FROM frecency
+             WHERE item_id NOT IN (SELECT item_id FROM hidden_items)",
+        )?;
         let data_iter = stmt.query_map([], |row| {
+            let item_id: String = row.get(0)?;
+            let source: Option<String> = row.get(1)?;
+            let last_used_at: i64 = row.get(3)?;
+            let score: f64 = row.get(4)?;
+            let weight = settings.weight(source.as_deref());
             Ok(FrecencyData {
-                item_id: row.get(0)?,
-                use_count: row.get(1)?,
-                last_used_at: row.get(2)?,
+                query_score: query_scores.get(&item_id).copied().unwrap_or(0.0) * weight,
+                item_id,
+                use_count: row.get(2)?,
+                last_used_at,
+                score: settings.decay(score, last_used_at, now) * weight,
+                source,
             })
         })?;
 
-        data_iter
-            .collect::<RusqliteResult<Vec<_>>>()
-            .map_err(|e| e.into())
+        let mut items = data_iter.collect::<RusqliteResult<Vec<_>>>()?;
+        items.sort_by(|a, b| (b.score + b.query_score).total_cmp(&(a.score + a.query_score)));
+        Ok(items)
     }
 
     pub fn delete_frecency_entry(&self, item_id: String) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
         db.execute("DELETE FROM frecency WHERE item_id = ?", params![item_id])?;
+        db.execute(
+            "DELETE FROM query_frecency WHERE item_id = ?",
+            params![item_id],
+        )?;
+        Ok(())
+    }
+
+    /// Forgets all usage of every item, including what was picked for which
+    /// query. Hidden items stay hidden.
+    pub fn reset_ranking_data(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        tx.execute("DELETE FROM frecency", [])?;
+        tx.execute("DELETE FROM query_frecency", [])?;
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn hide_item(&self, item_id: String) -> Result<(), AppError> {
+    pub fn hide_item(
+        &self,
+        item_id: String,
+        reason: Option<String>,
+        source: Option<String>,
+    ) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
         db.execute(
-            "INSERT OR IGNORE INTO hidden_items (item_id) VALUES (?)",
-            params![item_id],
+            "INSERT OR IGNORE INTO hidden_items (item_id, hidden_at, reason, source)
+             VALUES (?, ?, ?, ?)",
+            params![item_id, Utc::now().timestamp(), reason, source],
         )?;
         Ok(())
     }
 
-    pub fn get_hidden_item_ids(&self) -> Result<Vec<String>, AppError> {
+    pub fn unhide_items(&self, item_ids: Vec<String>) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM hidden_items WHERE item_id = ?")?;
+            for item_id in &item_ids {
+                stmt.execute(params![item_id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns hidden items, most recently hidden first.
+    pub fn get_hidden_items(&self) -> Result<Vec<HiddenItem>, AppError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare("SELECT item_id FROM hidden_items")?;
-        let ids_iter = stmt.query_map([], |row| row.get(0))?;
+        let mut stmt = db.prepare(
+            "SELECT item_id, hidden_at, reason, source FROM hidden_items
+             ORDER BY hidden_at DESC",
+        )?;
+        let items_iter = stmt.query_map([], |row| {
+            Ok(HiddenItem {
+                item_id: row.get(0)?,
+                hidden_at: row.get(1)?,
+                reason: row.get(2)?,
+                source: row.get(3)?,
+            })
+        })?;
 
-        ids_iter
-            .collect::<RusqliteResult<Vec<String>>>()
+        items_iter
+            .collect::<RusqliteResult<Vec<_>>>()
             .map_err(|e| e.into())
     }
 }
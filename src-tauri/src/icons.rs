@@ -0,0 +1,567 @@
+use crate::desktop::DesktopFileManager;
+use once_cell::sync::Lazy;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+const ICON_EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+const FALLBACK_THEME: &str = "hicolor";
+
+// reading the theme forks gsettings, so it is read once and then refreshed
+// when the desktop reports a change
+static CURRENT_THEME: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy)]
+enum SubdirType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+#[derive(Debug)]
+struct ThemeSubdir {
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    kind: SubdirType,
+}
+
+impl ThemeSubdir {
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            SubdirType::Fixed => self.size == size,
+            SubdirType::Scalable => self.min_size <= size && size <= self.max_size,
+            SubdirType::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size
+                    && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let target = size * scale;
+        let (min, max) = match self.kind {
+            SubdirType::Fixed => (self.size, self.size),
+            SubdirType::Scalable => (self.min_size, self.max_size),
+            SubdirType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+
+        // at most one of these is non-zero
+        min.saturating_sub(target) + target.saturating_sub(max)
+    }
+}
+
+/// An icon theme with every icon file it contains, indexed by icon name.
+struct IconTheme {
+    subdirs: Vec<ThemeSubdir>,
+    icons: HashMap<String, Vec<(usize, PathBuf)>>,
+}
+
+/// Resolves `Icon=` values to files following the freedesktop Icon Theme spec:
+/// the user's theme and the themes it inherits from, then `hicolor`, then the
+/// `pixmaps` directories.
+pub struct IconResolver {
+    themes: Vec<IconTheme>,
+    pixmap_dirs: Vec<PathBuf>,
+    size: u32,
+    resolved: Mutex<HashMap<String, Option<String>>>,
+}
+
+pub fn parse_ini(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+fn split_list(value: Option<&String>) -> Vec<String> {
+    value
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl IconResolver {
+    pub fn new(theme_name: &str, size: u32) -> Self {
+        let pixmap_dirs = DesktopFileManager::get_data_directories()
+            .into_iter()
+            .map(|dir| dir.join("pixmaps"))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        Self::with_directories(theme_name, size, &Self::get_base_directories(), pixmap_dirs)
+    }
+
+    fn with_directories(
+        theme_name: &str,
+        size: u32,
+        base_dirs: &[PathBuf],
+        pixmap_dirs: Vec<PathBuf>,
+    ) -> Self {
+        // each parent theme is searched along with everything it inherits
+        // before the next one, as the spec's lookup recurses into them
+        let mut themes = Vec::new();
+        let mut stack = vec![theme_name.to_string()];
+        let mut visited = HashSet::new();
+
+        while let Some(name) = stack.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some((theme, inherits)) = Self::load_theme(&name, base_dirs) {
+                themes.push(theme);
+                stack.extend(inherits.into_iter().rev());
+            }
+        }
+        if !visited.contains(FALLBACK_THEME) {
+            if let Some((theme, _)) = Self::load_theme(FALLBACK_THEME, base_dirs) {
+                themes.push(theme);
+            }
+        }
+
+        Self {
+            themes,
+            pixmap_dirs,
+            size,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the configured icon theme, as last read by `refresh_theme`.
+    pub fn current_theme() -> String {
+        let mut current = CURRENT_THEME.lock().unwrap();
+        current.get_or_insert_with(Self::read_theme).clone()
+    }
+
+    /// Reads the configured icon theme again and returns whether it changed.
+    pub fn refresh_theme() -> bool {
+        let theme = Self::read_theme();
+        let mut current = CURRENT_THEME.lock().unwrap();
+        let changed = current.as_ref() != Some(&theme);
+        *current = Some(theme);
+        changed
+    }
+
+    /// Reads the icon theme from GNOME's settings or from `kdeglobals`
+    /// depending on the running desktop.
+    fn read_theme() -> String {
+        let is_kde = DesktopFileManager::current_desktops()
+            .iter()
+            .any(|desktop| desktop == "KDE");
+
+        let theme = if is_kde {
+            Self::kde_theme().or_else(Self::gsettings_theme)
+        } else {
+            Self::gsettings_theme().or_else(Self::kde_theme)
+        };
+        theme.unwrap_or_else(|| FALLBACK_THEME.to_string())
+    }
+
+    fn gsettings_theme() -> Option<String> {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let theme = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .trim_matches('\'')
+            .to_string();
+        Some(theme).filter(|t| !t.is_empty())
+    }
+
+    fn kde_theme() -> Option<String> {
+        let config_dir = env::var("XDG_CONFIG_HOME")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var("HOME")
+                    .ok()
+                    .map(|h| PathBuf::from(h).join(".config"))
+            })?;
+        let content = fs::read_to_string(config_dir.join("kdeglobals")).ok()?;
+        parse_ini(&content)
+            .get("Icons")?
+            .get("Theme")
+            .cloned()
+            .filter(|t| !t.is_empty())
+    }
+
+    fn get_base_directories() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let Ok(home) = env::var("HOME") {
+            dirs.push(PathBuf::from(home).join(".icons"));
+        }
+        dirs.extend(
+            DesktopFileManager::get_data_directories()
+                .into_iter()
+                .map(|dir| dir.join("icons")),
+        );
+        dirs.retain(|dir| dir.is_dir());
+        dirs
+    }
+
+    fn load_theme(name: &str, base_dirs: &[PathBuf]) -> Option<(IconTheme, Vec<String>)> {
+        let theme_dirs: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        let index = theme_dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join("index.theme")).ok())?;
+        let sections = parse_ini(&index);
+        let header = sections.get("Icon Theme")?;
+
+        let mut subdir_names = split_list(header.get("Directories"));
+        subdir_names.extend(split_list(header.get("ScaledDirectories")));
+        let inherits = split_list(header.get("Inherits"));
+
+        let mut subdirs = Vec::new();
+        let mut icons: HashMap<String, Vec<(usize, PathBuf)>> = HashMap::new();
+
+        for subdir_name in subdir_names {
+            let Some(section) = sections.get(&subdir_name) else {
+                continue;
+            };
+            let get = |key: &str| section.get(key).and_then(|v| v.parse::<u32>().ok());
+            let Some(size) = get("Size") else {
+                continue;
+            };
+            let kind = match section.get("Type").map(String::as_str) {
+                Some("Fixed") => SubdirType::Fixed,
+                Some("Scalable") => SubdirType::Scalable,
+                _ => SubdirType::Threshold,
+            };
+
+            let subdir_index = subdirs.len();
+            subdirs.push(ThemeSubdir {
+                size,
+                scale: get("Scale").unwrap_or(1),
+                min_size: get("MinSize").unwrap_or(size),
+                max_size: get("MaxSize").unwrap_or(size),
+                threshold: get("Threshold").unwrap_or(2),
+                kind,
+            });
+
+            for theme_dir in &theme_dirs {
+                let Ok(entries) = fs::read_dir(theme_dir.join(&subdir_name)) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let (Some(stem), Some(ext)) = (
+                        path.file_stem().and_then(|s| s.to_str()),
+                        path.extension().and_then(|e| e.to_str()),
+                    ) else {
+                        continue;
+                    };
+                    if ICON_EXTENSIONS.contains(&ext) {
+                        icons
+                            .entry(stem.to_string())
+                            .or_default()
+                            .push((subdir_index, path));
+                    }
+                }
+            }
+        }
+
+        // png is preferred over svg over xpm when a directory has several
+        for candidates in icons.values_mut() {
+            candidates.sort_by_key(|(subdir, path)| {
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                (
+                    *subdir,
+                    ICON_EXTENSIONS
+                        .iter()
+                        .position(|e| *e == ext)
+                        .unwrap_or(usize::MAX),
+                )
+            });
+        }
+
+        Some((IconTheme { subdirs, icons }, inherits))
+    }
+
+    fn lookup_in_theme(&self, theme: &IconTheme, icon: &str) -> Option<PathBuf> {
+        let candidates = theme.icons.get(icon)?;
+
+        if let Some((_, path)) = candidates
+            .iter()
+            .find(|(subdir, _)| theme.subdirs[*subdir].matches_size(self.size, 1))
+        {
+            return Some(path.clone());
+        }
+
+        candidates
+            .iter()
+            .min_by_key(|(subdir, _)| theme.subdirs[*subdir].size_distance(self.size, 1))
+            .map(|(_, path)| path.clone())
+    }
+
+    fn lookup(&self, icon: &str) -> Option<String> {
+        let icon_path = Path::new(icon);
+        if icon_path.is_absolute() {
+            return icon_path.is_file().then(|| icon.to_string());
+        }
+
+        // some desktop files wrongly include the extension in the icon name
+        let icon = match icon_path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ICON_EXTENSIONS.contains(&ext) => icon_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(icon),
+            _ => icon,
+        };
+
+        self.themes
+            .iter()
+            .find_map(|theme| self.lookup_in_theme(theme, icon))
+            .or_else(|| {
+                self.pixmap_dirs.iter().find_map(|dir| {
+                    ICON_EXTENSIONS
+                        .iter()
+                        .map(|ext| dir.join(format!("{}.{}", icon, ext)))
+                        .find(|path| path.is_file())
+                })
+            })
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    pub fn resolve(&self, icon: &str) -> Option<String> {
+        if let Some(cached) = self.resolved.lock().unwrap().get(icon) {
+            return cached.clone();
+        }
+        let path = self.lookup(icon);
+        self.resolved
+            .lock()
+            .unwrap()
+            .insert(icon.to_string(), path.clone());
+        path
+    }
+
+    /// Reuses paths resolved by an earlier scan, skipping any that no longer exist.
+    pub fn seed(&self, resolved: &HashMap<String, String>) {
+        let mut cache = self.resolved.lock().unwrap();
+        for (icon, path) in resolved {
+            if Path::new(path).is_file() {
+                cache.insert(icon.clone(), Some(path.clone()));
+            }
+        }
+    }
+
+    /// Whether an icon couldn't be found since the last `forget_misses`.
+    pub fn has_misses(&self) -> bool {
+        self.resolved
+            .lock()
+            .unwrap()
+            .values()
+            .any(|path| path.is_none())
+    }
+
+    /// Forgets icons that couldn't be found, so they are looked up again.
+    pub fn forget_misses(&self) {
+        self.resolved
+            .lock()
+            .unwrap()
+            .retain(|_, path| path.is_some());
+    }
+
+    pub fn resolved_paths(&self) -> HashMap<String, String> {
+        self.resolved
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(icon, path)| path.clone().map(|p| (icon.clone(), p)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "raycast-linux-icons-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, path: &str, content: &str) -> String {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().into_owned()
+        }
+
+        fn resolver(&self, theme_name: &str, size: u32) -> IconResolver {
+            IconResolver::with_directories(
+                theme_name,
+                size,
+                &[self.0.join("icons")],
+                vec![self.0.join("pixmaps")],
+            )
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const SIZED_THEME: &str = "[Icon Theme]
+Name=Sized
+Directories=16x16/apps,48x48/apps,scalable/apps
+
+[16x16/apps]
+Size=16
+Type=Fixed
+
+[48x48/apps]
+Size=48
+Type=Threshold
+Threshold=4
+
+[scalable/apps]
+Size=128
+Type=Scalable
+MinSize=64
+MaxSize=256
+";
+
+    fn theme(dir: &TestDir, name: &str, inherits: &str) {
+        dir.write(
+            &format!("icons/{}/index.theme", name),
+            &format!(
+                "[Icon Theme]\nName={}\nInherits={}\nDirectories=apps\n\n[apps]\nSize=48\n",
+                name, inherits
+            ),
+        );
+    }
+
+    #[test]
+    fn subdirs_match_by_their_type() {
+        let dir = TestDir::new("match");
+        dir.write("icons/sized/index.theme", SIZED_THEME);
+        let fixed = dir.write("icons/sized/16x16/apps/app.png", "");
+        let threshold = dir.write("icons/sized/48x48/apps/app.png", "");
+        let scalable = dir.write("icons/sized/scalable/apps/app.svg", "");
+
+        let resolve = |size| dir.resolver("sized", size).resolve("app");
+        assert_eq!(resolve(16), Some(fixed));
+        assert_eq!(resolve(44), Some(threshold.clone()));
+        assert_eq!(resolve(52), Some(threshold));
+        assert_eq!(resolve(64), Some(scalable.clone()));
+        assert_eq!(resolve(256), Some(scalable));
+    }
+
+    #[test]
+    fn the_closest_size_is_used_when_none_match() {
+        let dir = TestDir::new("closest");
+        dir.write("icons/sized/index.theme", SIZED_THEME);
+        let fixed = dir.write("icons/sized/16x16/apps/app.png", "");
+        let threshold = dir.write("icons/sized/48x48/apps/app.png", "");
+        let scalable = dir.write("icons/sized/scalable/apps/app.svg", "");
+
+        let resolve = |size| dir.resolver("sized", size).resolve("app");
+        assert_eq!(resolve(22), Some(fixed));
+        assert_eq!(resolve(36), Some(threshold));
+        assert_eq!(resolve(512), Some(scalable));
+    }
+
+    #[test]
+    fn inherited_themes_are_searched_depth_first_then_hicolor() {
+        let dir = TestDir::new("inherits");
+        theme(&dir, "user", "parent,other");
+        theme(&dir, "parent", "grandparent");
+        theme(&dir, "grandparent", "");
+        theme(&dir, "other", "");
+        theme(&dir, "hicolor", "");
+        let own = dir.write("icons/user/apps/own.png", "");
+        dir.write("icons/parent/apps/own.png", "");
+        let deep = dir.write("icons/grandparent/apps/deep.png", "");
+        dir.write("icons/other/apps/deep.png", "");
+        let sibling = dir.write("icons/other/apps/sibling.png", "");
+        dir.write("icons/hicolor/apps/sibling.png", "");
+        let fallback = dir.write("icons/hicolor/apps/fallback.png", "");
+
+        let resolver = dir.resolver("user", 48);
+        assert_eq!(resolver.resolve("own"), Some(own));
+        assert_eq!(resolver.resolve("deep"), Some(deep));
+        assert_eq!(resolver.resolve("sibling"), Some(sibling));
+        assert_eq!(resolver.resolve("fallback"), Some(fallback));
+    }
+
+    #[test]
+    fn png_is_preferred_over_svg_over_xpm() {
+        let dir = TestDir::new("formats");
+        theme(&dir, "user", "");
+        let png = dir.write("icons/user/apps/all.png", "");
+        dir.write("icons/user/apps/all.svg", "");
+        dir.write("icons/user/apps/all.xpm", "");
+        let svg = dir.write("icons/user/apps/vector.svg", "");
+        dir.write("icons/user/apps/vector.xpm", "");
+
+        let resolver = dir.resolver("user", 48);
+        assert_eq!(resolver.resolve("all"), Some(png.clone()));
+        assert_eq!(resolver.resolve("all.svg"), Some(png));
+        assert_eq!(resolver.resolve("vector"), Some(svg));
+    }
+
+    #[test]
+    fn pixmaps_are_searched_after_the_themes() {
+        let dir = TestDir::new("pixmaps");
+        theme(&dir, "user", "");
+        let themed = dir.write("icons/user/apps/both.png", "");
+        dir.write("pixmaps/both.png", "");
+        let pixmap = dir.write("pixmaps/legacy.xpm", "");
+
+        let resolver = dir.resolver("user", 48);
+        assert_eq!(resolver.resolve("both"), Some(themed));
+        assert_eq!(resolver.resolve("legacy"), Some(pixmap));
+        assert_eq!(resolver.resolve("missing"), None);
+        assert!(resolver.has_misses());
+    }
+}
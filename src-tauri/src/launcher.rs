@@ -0,0 +1,334 @@
+use crate::{app::App, cache::AppCache, desktop::DesktopFileManager, error::AppError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use zbus::zvariant::Value;
+
+// terminal emulators tried in order when none is configured, with the
+// arguments that precede the command to run
+const KNOWN_TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("kgx", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("ghostty", &["-e"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("xterm", &["-e"]),
+];
+
+// variables that describe the launcher's own packaging and would confuse
+// the apps it starts
+const LAUNCHER_ONLY_ENV_VARS: &[&str] = &["APPDIR", "APPIMAGE", "ARGV0", "OWD"];
+
+/// Per-app adjustments applied when launching, keyed by desktop-file ID.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLaunchOverride {
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub unset_env: Vec<String>,
+    pub working_directory: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchSettings {
+    /// Command used to run `Terminal=true` apps, such as `alacritty -e`. The
+    /// app's own command line is appended to it.
+    pub terminal_command: Option<String>,
+    #[serde(default = "default_use_systemd_scopes")]
+    pub use_systemd_scopes: bool,
+    #[serde(default)]
+    pub app_overrides: HashMap<String, AppLaunchOverride>,
+}
+
+fn default_use_systemd_scopes() -> bool {
+    true
+}
+
+impl Default for LaunchSettings {
+    fn default() -> Self {
+        Self {
+            terminal_command: None,
+            use_systemd_scopes: default_use_systemd_scopes(),
+            app_overrides: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchedProcess {
+    pub pid: u32,
+    pub scope_unit: Option<String>,
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|_| "Failed to get app local data dir".to_string())?;
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("launch_settings.json"))
+}
+
+fn read_settings(path: &Path) -> Result<LaunchSettings, String> {
+    if !path.exists() {
+        return Ok(LaunchSettings::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(LaunchSettings::default());
+    }
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn write_settings(path: &Path, settings: &LaunchSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_launch_settings(app: AppHandle) -> Result<LaunchSettings, String> {
+    read_settings(&get_settings_path(&app)?)
+}
+
+#[tauri::command]
+pub fn set_launch_settings(app: AppHandle, settings: LaunchSettings) -> Result<(), String> {
+    write_settings(&get_settings_path(&app)?, &settings)
+}
+
+fn terminal_command(settings: &LaunchSettings) -> Result<Vec<String>, AppError> {
+    if let Some(command) = settings
+        .terminal_command
+        .as_deref()
+        .filter(|command| !command.trim().is_empty())
+    {
+        return Ok(DesktopFileManager::split_exec(command)?
+            .into_iter()
+            .map(|(arg, _)| arg)
+            .collect());
+    }
+
+    if let Ok(terminal) = env::var("TERMINAL") {
+        if !terminal.is_empty() && DesktopFileManager::is_executable_available(&terminal) {
+            return Ok(vec![terminal, "-e".to_string()]);
+        }
+    }
+
+    KNOWN_TERMINALS
+        .iter()
+        .find(|(program, _)| DesktopFileManager::is_executable_available(program))
+        .map(|(program, args)| {
+            std::iter::once(program.to_string())
+                .chain(args.iter().map(|arg| arg.to_string()))
+                .collect()
+        })
+        .ok_or_else(|| {
+            AppError::DesktopEntry(
+                "No terminal emulator found. Set one in the launch settings.".to_string(),
+            )
+        })
+}
+
+pub fn find_installed_app(predicate: impl Fn(&App) -> bool) -> Option<App> {
+    AppCache::get_apps()
+        .ok()
+        .and_then(|apps| apps.into_iter().find(|app| predicate(app)))
+}
+
+/// Escapes a string for use in a systemd unit name, like `systemd-escape`.
+fn escape_unit_name(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (i, byte) in value.bytes().enumerate() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b':' | b'_' => escaped.push(byte as char),
+            b'.' if i > 0 => escaped.push('.'),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Moves the running process `pid` into a new transient
+/// `app-<id>-<random>.scope` of the user's systemd instance, the way desktop
+/// shells group the apps they start, and returns the unit's name. Anything
+/// the app forked before the move stays in the launcher's cgroup, which is
+/// why this runs straight after the spawn.
+fn start_systemd_scope(app: &App, pid: u32) -> Result<String, AppError> {
+    let systemd_error = |e: zbus::Error| AppError::DesktopEntry(format!("systemd error: {}", e));
+
+    let app_id = app
+        .desktop_file_id
+        .as_deref()
+        .map(|id| id.strip_suffix(".desktop").unwrap_or(id))
+        .filter(|id| !id.is_empty())
+        .unwrap_or("unknown");
+    let unit_name = format!(
+        "app-{}-{:08x}.scope",
+        escape_unit_name(app_id),
+        rand::random::<u32>()
+    );
+
+    let properties: Vec<(&str, Value)> = vec![
+        (
+            "Description",
+            Value::from(format!("Application launched by Raycast: {}", app.name)),
+        ),
+        ("PIDs", Value::from(vec![pid])),
+        ("CollectMode", Value::from("inactive-or-failed")),
+    ];
+    let auxiliary_units: Vec<(&str, Vec<(&str, Value)>)> = Vec::new();
+
+    let connection = zbus::blocking::Connection::session().map_err(systemd_error)?;
+    connection
+        .call_method(
+            Some("org.freedesktop.systemd1"),
+            "/org/freedesktop/systemd1",
+            Some("org.freedesktop.systemd1.Manager"),
+            "StartTransientUnit",
+            &(unit_name.as_str(), "fail", properties, auxiliary_units),
+        )
+        .map_err(systemd_error)?;
+    Ok(unit_name)
+}
+
+fn build_command(
+    command_line: &[String],
+    app: &App,
+    overrides: Option<&AppLaunchOverride>,
+) -> Command {
+    let mut command = Command::new(&command_line[0]);
+    command.args(&command_line[1..]);
+
+    for var in LAUNCHER_ONLY_ENV_VARS {
+        command.env_remove(var);
+    }
+
+    let working_directory = overrides
+        .and_then(|o| o.working_directory.clone())
+        .or_else(|| app.working_directory.clone())
+        .or_else(|| env::var("HOME").ok())
+        .filter(|dir| Path::new(dir).is_dir());
+    if let Some(dir) = working_directory {
+        command.current_dir(dir);
+    }
+
+    if let Some(overrides) = overrides {
+        for var in &overrides.unset_env {
+            command.env_remove(var);
+        }
+        command.envs(&overrides.env);
+    }
+
+    command
+}
+
+/// Starts `command` as a grandchild of the launcher: the child forks again
+/// and exits straight away, so the app is reparented to init (or the
+/// session's subreaper) and never has to be waited for. Returns the app's pid.
+fn spawn_detached(mut command: Command) -> io::Result<u32> {
+    use std::os::unix::process::CommandExt;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let (pid_reader, pid_writer) =
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    let pid_writer_fd = pid_writer.as_raw_fd();
+
+    // only async-signal-safe calls are allowed between fork and exec
+    unsafe {
+        command.pre_exec(move || match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => {
+                // a session of its own, so the app isn't tied to the launcher's
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            }
+            pid => {
+                let bytes = pid.to_ne_bytes();
+                libc::write(pid_writer_fd, bytes.as_ptr().cast(), bytes.len());
+                libc::_exit(0)
+            }
+        });
+    }
+
+    // spawn returns once the grandchild has exec'd or failed to, and the
+    // intermediate child has exited by then or is about to
+    let spawned = command.spawn().and_then(|mut child| child.wait());
+    drop(pid_writer);
+    spawned?;
+
+    let mut bytes = [0; 4];
+    File::from(pid_reader).read_exact(&mut bytes)?;
+    Ok(i32::from_ne_bytes(bytes) as u32)
+}
+
+/// Launches `exec` on behalf of `app`, wrapping it in a terminal emulator when
+/// the app asks for one. Each process is detached from the launcher and, when
+/// possible, started in its own systemd scope.
+pub fn spawn_exec(
+    app_handle: &AppHandle,
+    exec: &str,
+    app: &App,
+    targets: &[String],
+) -> Result<Vec<LaunchedProcess>, String> {
+    let settings = read_settings(&get_settings_path(app_handle)?)?;
+    let mut commands =
+        DesktopFileManager::build_exec_commands(exec, app, targets).map_err(|e| e.to_string())?;
+
+    if app.terminal {
+        let terminal = terminal_command(&settings).map_err(|e| e.to_string())?;
+        for command_line in commands.iter_mut() {
+            command_line.splice(0..0, terminal.iter().cloned());
+        }
+    }
+
+    let overrides = app
+        .desktop_file_id
+        .as_ref()
+        .and_then(|id| settings.app_overrides.get(id));
+
+    let mut launched = Vec::new();
+    for command_line in commands {
+        let pid = spawn_detached(build_command(&command_line, app, overrides))
+            .map_err(|e| format!("Failed to launch app: {}", e))?;
+        if let Some(id) = &app.desktop_file_id {
+            crate::running_apps::record_launch(id, pid);
+        }
+
+        let scope_unit = if settings.use_systemd_scopes {
+            match start_systemd_scope(app, pid) {
+                Ok(unit) => Some(unit),
+                Err(e) => {
+                    eprintln!("Failed to create systemd scope for {}: {}", app.name, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        launched.push(LaunchedProcess { pid, scope_unit });
+    }
+
+    Ok(launched)
+}
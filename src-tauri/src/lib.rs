@@ -1,5 +1,7 @@
 mod ai;
 mod app;
+mod app_watcher;
+mod backup;
 mod browser_extension;
 mod cache;
 mod clipboard;
@@ -7,14 +9,22 @@ pub mod clipboard_history;
 mod desktop;
 mod error;
 mod extensions;
+mod favicon;
+mod file_managers;
 mod file_search;
 mod filesystem;
 mod frecency;
+mod icons;
+mod launcher;
+mod migrations;
+mod mime;
 mod oauth;
 mod quicklinks;
+mod running_apps;
 mod snippets;
 mod soulver;
 mod system;
+mod window_manager;
 
 use crate::snippets::input_manager::{EvdevInputManager, InputManager};
 use crate::{app::App, cache::AppCache};
@@ -25,10 +35,8 @@ use quicklinks::QuicklinkManager;
 use selection::get_text;
 use snippets::engine::ExpansionEngine;
 use snippets::manager::SnippetManager;
-use std::process::Command;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
 use tauri::{Emitter, Manager};
 
 #[tauri::command]
@@ -43,24 +51,50 @@ fn get_installed_apps() -> Vec<App> {
 }
 
 #[tauri::command]
-fn launch_app(exec: String) -> Result<(), String> {
-    let exec_parts: Vec<&str> = exec.split_whitespace().collect();
-    if exec_parts.is_empty() {
-        return Err("Empty exec command".to_string());
+fn launch_app(
+    app_handle: tauri::AppHandle,
+    exec: String,
+    targets: Option<Vec<String>>,
+    new_instance: Option<bool>,
+) -> Result<Vec<launcher::LaunchedProcess>, String> {
+    let app = launcher::find_installed_app(|app| app.exec.as_deref() == Some(exec.as_str()))
+        .unwrap_or_else(|| App::new(String::new()).with_exec(Some(exec.clone())));
+
+    // bring an already running instance to the front instead of starting another
+    let targets = targets.unwrap_or_default();
+    if !new_instance.unwrap_or(false)
+        && targets.is_empty()
+        && app.desktop_file_id.is_some()
+        && running_apps::focus_running_instance(&app)
+    {
+        return Ok(Vec::new());
     }
 
-    let mut command = Command::new(exec_parts[0]);
-    for arg in &exec_parts[1..] {
-        if !arg.starts_with('%') {
-            command.arg(arg);
-        }
-    }
-
-    command
-        .spawn()
-        .map_err(|e| format!("Failed to launch app: {}", e))?;
+    launcher::spawn_exec(&app_handle, &exec, &app, &targets)
+}
 
-    Ok(())
+#[tauri::command]
+fn launch_app_action(
+    app_handle: tauri::AppHandle,
+    desktop_file_id: String,
+    action_id: String,
+    targets: Option<Vec<String>>,
+) -> Result<Vec<launcher::LaunchedProcess>, String> {
+    let app = launcher::find_installed_app(|app| {
+        app.desktop_file_id.as_deref() == Some(desktop_file_id.as_str())
+    })
+    .ok_or_else(|| format!("No installed app with id {}", desktop_file_id))?;
+
+    let exec = app
+        .actions
+        .iter()
+        .find(|action| action.id == action_id)
+        .ok_or_else(|| format!("App has no action '{}'", action_id))?
+        .exec
+        .clone()
+        .ok_or_else(|| format!("Action '{}' has no exec command", action_id))?;
+
+    launcher::spawn_exec(&app_handle, &exec, &app, &targets.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -106,16 +140,39 @@ async fn show_hud(app: tauri::AppHandle, title: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn record_usage(app: tauri::AppHandle, item_id: String) -> Result<(), String> {
+fn record_usage(
+    app: tauri::AppHandle,
+    item_id: String,
+    source: Option<String>,
+    query: Option<String>,
+) -> Result<(), String> {
     app.state::<FrecencyManager>()
-        .record_usage(item_id)
+        .record_usage(item_id, source, query)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_frecency_data(app: tauri::AppHandle) -> Result<Vec<frecency::FrecencyData>, String> {
+fn get_ranked_items(
+    app: tauri::AppHandle,
+    query: Option<String>,
+) -> Result<Vec<frecency::FrecencyData>, String> {
     app.state::<FrecencyManager>()
-        .get_frecency_data()
+        .get_ranked_items(query)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_frecency_settings(app: tauri::AppHandle) -> frecency::FrecencySettings {
+    app.state::<FrecencyManager>().get_settings()
+}
+
+#[tauri::command]
+fn set_frecency_settings(
+    app: tauri::AppHandle,
+    settings: frecency::FrecencySettings,
+) -> Result<(), String> {
+    app.state::<FrecencyManager>()
+        .set_settings(settings)
         .map_err(|e| e.to_string())
 }
 
@@ -127,27 +184,36 @@ fn delete_frecency_entry(app: tauri::AppHandle, item_id: String) -> Result<(), S
 }
 
 #[tauri::command]
-fn hide_item(app: tauri::AppHandle, item_id: String) -> Result<(), String> {
+fn reset_ranking_data(app: tauri::AppHandle) -> Result<(), String> {
     app.state::<FrecencyManager>()
-        .hide_item(item_id)
+        .reset_ranking_data()
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn get_hidden_item_ids(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+fn hide_item(
+    app: tauri::AppHandle,
+    item_id: String,
+    reason: Option<String>,
+    source: Option<String>,
+) -> Result<(), String> {
     app.state::<FrecencyManager>()
-        .get_hidden_item_ids()
+        .hide_item(item_id, reason, source)
         .map_err(|e| e.to_string())
 }
 
-fn setup_background_refresh() {
-    thread::spawn(|| {
-        thread::sleep(Duration::from_secs(60));
-        loop {
-            AppCache::refresh_background();
-            thread::sleep(Duration::from_secs(300));
-        }
-    });
+#[tauri::command]
+fn unhide_items(app: tauri::AppHandle, item_ids: Vec<String>) -> Result<(), String> {
+    app.state::<FrecencyManager>()
+        .unhide_items(item_ids)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_hidden_items(app: tauri::AppHandle) -> Result<Vec<frecency::HiddenItem>, String> {
+    app.state::<FrecencyManager>()
+        .get_hidden_items()
+        .map_err(|e| e.to_string())
 }
 
 fn setup_global_shortcut(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
@@ -170,6 +236,7 @@ fn setup_global_shortcut(app: &mut tauri::App) -> Result<(), Box<dyn std::error:
                     if spotlight_window.is_visible().unwrap_or(false) {
                         spotlight_window.hide().unwrap();
                     } else {
+                        window_manager::remember_previous_window();
                         spotlight_window.show().unwrap();
                         spotlight_window.set_focus().unwrap();
                     }
@@ -210,6 +277,7 @@ pub fn run() {
             if args.len() > 1 && args[1].starts_with("raycast://") {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.emit("deep-link", args[1].to_string());
+                    window_manager::remember_previous_window();
                     window.show().unwrap();
                     window.set_focus().unwrap();
                 }
@@ -220,6 +288,7 @@ pub fn run() {
                 if let Ok(true) = window.is_visible() {
                     let _ = window.hide();
                 } else {
+                    window_manager::remember_previous_window();
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
@@ -233,6 +302,12 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             get_installed_apps,
             launch_app,
+            launch_app_action,
+            launcher::get_launch_settings,
+            launcher::set_launch_settings,
+            running_apps::get_running_apps,
+            running_apps::quit_app,
+            running_apps::force_quit_app,
             get_selected_text,
             show_hud,
             filesystem::get_selected_finder_items,
@@ -258,16 +333,30 @@ pub fn run() {
             quicklinks::update_quicklink,
             quicklinks::delete_quicklink,
             quicklinks::execute_quicklink,
+            quicklinks::get_quicklink_arguments,
+            quicklinks::get_quicklink_by_alias,
+            quicklinks::quicklink_was_used,
+            quicklinks::import_quicklinks,
+            quicklinks::export_quicklinks,
+            quicklinks::refresh_quicklink_favicons,
             system::get_applications,
             system::get_default_application,
             system::get_frontmost_application,
             system::show_in_finder,
             system::trash,
+            system::list_trash,
+            system::restore_trash_items,
+            system::purge_trash_items,
+            system::empty_trash,
             record_usage,
-            get_frecency_data,
+            get_ranked_items,
+            get_frecency_settings,
+            set_frecency_settings,
             delete_frecency_entry,
+            reset_ranking_data,
             hide_item,
-            get_hidden_item_ids,
+            unhide_items,
+            get_hidden_items,
             snippets::create_snippet,
             snippets::list_snippets,
             snippets::update_snippet,
@@ -275,6 +364,8 @@ pub fn run() {
             snippets::import_snippets,
             snippets::paste_snippet_content,
             snippets::snippet_was_used,
+            snippets::get_snippet_settings,
+            snippets::set_snippet_settings,
             file_search::search_files,
             ai::set_ai_api_key,
             ai::is_ai_api_key_set,
@@ -284,7 +375,9 @@ pub fn run() {
             ai::get_ai_settings,
             ai::set_ai_settings,
             ai::ai_can_access,
-            soulver::calculate_soulver
+            soulver::calculate_soulver,
+            backup::export_backup,
+            backup::import_backup
         ])
         .setup(|app| {
             let app_handle = app.handle().clone();
@@ -311,7 +404,9 @@ pub fn run() {
             ai_usage_manager.init_db()?;
             app.manage(ai_usage_manager);
 
-            setup_background_refresh();
+            if let Err(e) = app_watcher::start_watching(app.handle().clone()) {
+                eprintln!("Failed to watch application directories: {:?}", e);
+            }
             setup_global_shortcut(app)?;
             setup_input_listener(app.handle());
 
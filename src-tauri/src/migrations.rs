@@ -0,0 +1,240 @@
+use crate::error::AppError;
+use rusqlite::{Connection, Result as RusqliteResult};
+use std::path::PathBuf;
+
+enum Step {
+    Sql(&'static str),
+    Code(fn(&Connection) -> RusqliteResult<()>),
+}
+
+/// One schema change of a database. A database's migrations are numbered
+/// from 1 in the order they appear, and its `user_version` records how many
+/// have been applied. Append new migrations; never edit or reorder shipped
+/// ones.
+pub struct Migration {
+    description: &'static str,
+    step: Step,
+}
+
+impl Migration {
+    pub const fn sql(description: &'static str, sql: &'static str) -> Self {
+        Self {
+            description,
+            step: Step::Sql(sql),
+        }
+    }
+
+    /// A migration that needs to inspect the schema or data first.
+    pub const fn code(
+        description: &'static str,
+        up: fn(&Connection) -> RusqliteResult<()>,
+    ) -> Self {
+        Self {
+            description,
+            step: Step::Code(up),
+        }
+    }
+
+    fn apply(&self, db: &Connection) -> RusqliteResult<()> {
+        match self.step {
+            Step::Sql(sql) => db.execute_batch(sql),
+            Step::Code(up) => up(db),
+        }
+    }
+}
+
+/// Adds a column unless the table already has it. Databases created before
+/// versioning may have received columns through ad-hoc checks, so early
+/// migrations have to tolerate them. Returns whether the column was added.
+pub fn add_column_if_missing(
+    db: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> RusqliteResult<bool> {
+    let mut stmt = db.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if columns.iter().any(|existing| existing == column) {
+        return Ok(false);
+    }
+    db.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        [],
+    )?;
+    Ok(true)
+}
+
+fn user_version(db: &Connection) -> RusqliteResult<usize> {
+    db.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map(|version| version.max(0) as usize)
+}
+
+fn has_schema(db: &Connection) -> RusqliteResult<bool> {
+    db.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|count| count > 0)
+}
+
+/// Copies the database next to itself as `<name>.v<version>.bak`, replacing
+/// an older backup of the same version.
+fn backup(db: &Connection, version: usize) -> Result<(), AppError> {
+    let Some(path) = db.path().filter(|path| !path.is_empty()) else {
+        // in-memory databases have nothing to lose
+        return Ok(());
+    };
+    let backup_path = PathBuf::from(format!("{}.v{}.bak", path, version));
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path)?;
+    }
+    db.execute("VACUUM INTO ?", [backup_path.to_string_lossy().as_ref()])?;
+    Ok(())
+}
+
+/// Brings a database up to date with `migrations`. Pending migrations run in
+/// a single transaction, so a failure leaves the database at its previous
+/// version, and an existing database is backed up before it is touched.
+pub fn run(db: &mut Connection, migrations: &[Migration]) -> Result<(), AppError> {
+    apply_pending(db, migrations, true)
+}
+
+/// Like [`run`], but without the backup, for throwaway copies such as a
+/// database read from a backup archive.
+pub fn run_without_backup(db: &mut Connection, migrations: &[Migration]) -> Result<(), AppError> {
+    apply_pending(db, migrations, false)
+}
+
+fn apply_pending(
+    db: &mut Connection,
+    migrations: &[Migration],
+    backup_first: bool,
+) -> Result<(), AppError> {
+    let version = user_version(db)?;
+    if version > migrations.len() {
+        return Err(AppError::Migration(format!(
+            "{} has schema version {}, but this version of the app only knows {}",
+            db.path().unwrap_or("database"),
+            version,
+            migrations.len()
+        )));
+    }
+    if version == migrations.len() {
+        return Ok(());
+    }
+
+    if backup_first && has_schema(db)? {
+        backup(db, version)?;
+    }
+
+    let tx = db.transaction()?;
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        migration.apply(&tx).map_err(|e| {
+            AppError::Migration(format!(
+                "Migration {} ({}) failed: {}",
+                index + 1,
+                migration.description,
+                e
+            ))
+        })?;
+    }
+    tx.pragma_update(None, "user_version", migrations.len() as i64)?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "raycast-linux-migrations-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const CREATE_NOTES: Migration = Migration::sql(
+        "create notes table",
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL)",
+    );
+    const ADD_TITLE: Migration =
+        Migration::sql("add note titles", "ALTER TABLE notes ADD COLUMN title TEXT");
+    const BROKEN: Migration = Migration::sql(
+        "half of it works",
+        "CREATE TABLE tags (name TEXT); INSERT INTO missing VALUES (1);",
+    );
+
+    fn table_exists(db: &Connection, name: &str) -> bool {
+        db.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [name],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn a_failing_migration_changes_nothing() {
+        let mut db = Connection::open_in_memory().unwrap();
+        run(&mut db, &[CREATE_NOTES]).unwrap();
+
+        let error = run(&mut db, &[CREATE_NOTES, ADD_TITLE, BROKEN]).unwrap_err();
+        assert!(error.to_string().contains("Migration 3 (half of it works)"));
+        assert_eq!(user_version(&db).unwrap(), 1);
+        assert!(!table_exists(&db, "tags"));
+        assert!(!add_column_if_missing(&db, "notes", "body", "TEXT").unwrap());
+        assert!(add_column_if_missing(&db, "notes", "title", "TEXT").unwrap());
+    }
+
+    #[test]
+    fn databases_are_backed_up_before_upgrading() {
+        let dir = TestDir::new("backup");
+        let path = dir.0.join("notes.sqlite");
+        let backup_path = dir.0.join("notes.sqlite.v1.bak");
+
+        let mut db = Connection::open(&path).unwrap();
+        run(&mut db, &[CREATE_NOTES]).unwrap();
+        // a new database has nothing worth keeping
+        assert!(!dir.0.join("notes.sqlite.v0.bak").exists());
+        db.execute("INSERT INTO notes (body) VALUES ('kept')", [])
+            .unwrap();
+        fs::write(&backup_path, "an older backup").unwrap();
+
+        run(&mut db, &[CREATE_NOTES, ADD_TITLE]).unwrap();
+        assert_eq!(user_version(&db).unwrap(), 2);
+
+        let backup = Connection::open(&backup_path).unwrap();
+        assert_eq!(user_version(&backup).unwrap(), 1);
+        let body: String = backup
+            .query_row("SELECT body FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(body, "kept");
+        assert!(add_column_if_missing(&backup, "notes", "title", "TEXT").unwrap());
+    }
+
+    #[test]
+    fn databases_from_a_newer_version_are_refused() {
+        let mut db = Connection::open_in_memory().unwrap();
+        run(&mut db, &[CREATE_NOTES, ADD_TITLE]).unwrap();
+        assert!(run(&mut db, &[CREATE_NOTES]).is_err());
+        assert_eq!(user_version(&db).unwrap(), 2);
+    }
+}
@@ -0,0 +1,1127 @@
+use crate::{app::App, desktop::DesktopFileManager, icons::parse_ini};
+use once_cell::sync::Lazy;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_GLOB_WEIGHT: u32 = 50;
+// how much of a file is read for magic sniffing, whatever the rules ask for
+const MAX_MAGIC_BYTES: usize = 256 * 1024;
+
+static MIME_DATABASE: Lazy<MimeDatabase> = Lazy::new(MimeDatabase::load);
+
+struct GlobRule {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+struct MagicRule {
+    indent: u32,
+    offset: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    range: usize,
+}
+
+struct MagicSection {
+    priority: u32,
+    mime_type: String,
+    rules: Vec<MagicRule>,
+}
+
+/// The parts of the shared-mime-info database needed to tell a file's type:
+/// filename globs, content magic, aliases and the subclass hierarchy.
+struct MimeDatabase {
+    globs: Vec<GlobRule>,
+    magic: Vec<MagicSection>,
+    aliases: HashMap<String, String>,
+    parents: HashMap<String, Vec<String>>,
+}
+
+/// Matches `name` against a shell glob supporting `*`, `?` and `[...]`.
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = match_class(pattern, p, name[n]) {
+                        if matched {
+                            p = next;
+                            n += 1;
+                            continue;
+                        }
+                    } else if name[n] == '[' {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+                c if c == name[n] => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        // retry the last `*` consuming one more character
+        match backtrack {
+            Some((star, consumed)) => {
+                p = star + 1;
+                n = consumed + 1;
+                backtrack = Some((star, consumed + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Matches `c` against the bracket expression starting at `pattern[start]`.
+/// Returns whether it matched and the index after the expression, or `None`
+/// when the bracket is never closed.
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        if pattern[i] == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|end| *end != ']') {
+            if pattern[i] <= c && c <= pattern[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if pattern[i] == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+fn read_number(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let start = *pos;
+    while data.get(*pos).is_some_and(|b| b.is_ascii_digit()) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&data[start..*pos]).ok()?.parse().ok()
+}
+
+/// Parses the binary `magic` file written by `update-mime-database`.
+fn parse_magic(data: &[u8]) -> Option<Vec<MagicSection>> {
+    let mut pos = data.strip_prefix(b"MIME-Magic\0\n").map(|_| 12)?;
+    let mut sections: Vec<MagicSection> = Vec::new();
+
+    while pos < data.len() {
+        if data[pos] == b'[' {
+            let end = pos + data[pos..].iter().position(|b| *b == b'\n')?;
+            let header = std::str::from_utf8(&data[pos + 1..end - 1]).ok()?;
+            let (priority, mime_type) = header.split_once(':')?;
+            sections.push(MagicSection {
+                priority: priority.parse().ok()?,
+                mime_type: mime_type.to_string(),
+                rules: Vec::new(),
+            });
+            pos = end + 1;
+            continue;
+        }
+
+        let indent = if data[pos] == b'>' {
+            0
+        } else {
+            read_number(data, &mut pos)? as u32
+        };
+        if data.get(pos) != Some(&b'>') {
+            return None;
+        }
+        pos += 1;
+        let offset = read_number(data, &mut pos)?;
+        if data.get(pos) != Some(&b'=') {
+            return None;
+        }
+        pos += 1;
+        let length = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        pos += 2;
+        let mut value = data.get(pos..pos + length)?.to_vec();
+        pos += length;
+
+        let mut mask = None;
+        let mut word_size = 1;
+        let mut range = 1;
+        loop {
+            match data.get(pos)? {
+                b'&' => {
+                    mask = Some(data.get(pos + 1..pos + 1 + length)?.to_vec());
+                    pos += 1 + length;
+                }
+                b'~' => {
+                    pos += 1;
+                    word_size = read_number(data, &mut pos)?;
+                }
+                b'+' => {
+                    pos += 1;
+                    range = read_number(data, &mut pos)?;
+                }
+                b'\n' => {
+                    pos += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+
+        // values are stored big-endian and swapped per word on little-endian hosts
+        if cfg!(target_endian = "little") && word_size > 1 {
+            value.chunks_mut(word_size).for_each(|word| word.reverse());
+            if let Some(mask) = mask.as_mut() {
+                mask.chunks_mut(word_size).for_each(|word| word.reverse());
+            }
+        }
+
+        sections.last_mut()?.rules.push(MagicRule {
+            indent,
+            offset,
+            value,
+            mask,
+            range: range.max(1),
+        });
+    }
+
+    Some(sections)
+}
+
+impl MagicRule {
+    fn matches(&self, data: &[u8]) -> bool {
+        (self.offset..self.offset + self.range).any(|start| {
+            let Some(window) = data.get(start..start + self.value.len()) else {
+                return false;
+            };
+            match &self.mask {
+                Some(mask) => window
+                    .iter()
+                    .zip(&self.value)
+                    .zip(mask)
+                    .all(|((byte, value), mask)| byte & mask == value & mask),
+                None => window == self.value.as_slice(),
+            }
+        })
+    }
+
+    fn bytes_needed(&self) -> usize {
+        self.offset + self.range + self.value.len()
+    }
+}
+
+impl MagicSection {
+    /// A rule matches when its own test passes and, if it has nested rules,
+    /// at least one of them matches too.
+    fn rules_match(&self, start: usize, indent: u32, data: &[u8]) -> bool {
+        let mut i = start;
+        while i < self.rules.len() && self.rules[i].indent >= indent {
+            if self.rules[i].indent == indent && self.rules[i].matches(data) {
+                let has_children = self
+                    .rules
+                    .get(i + 1)
+                    .is_some_and(|next| next.indent > indent);
+                if !has_children || self.rules_match(i + 1, indent + 1, data) {
+                    return true;
+                }
+            }
+            i += 1;
+        }
+        false
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        self.rules_match(0, 0, data)
+    }
+}
+
+fn mime_directories() -> Vec<PathBuf> {
+    DesktopFileManager::get_data_directories()
+        .into_iter()
+        .map(|dir| dir.join("mime"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+impl MimeDatabase {
+    fn load() -> Self {
+        Self::load_from(&mime_directories())
+    }
+
+    /// Reads the database from `dirs`, given in precedence order.
+    fn load_from(dirs: &[PathBuf]) -> Self {
+        let mut database = MimeDatabase {
+            globs: Vec::new(),
+            magic: Vec::new(),
+            aliases: HashMap::new(),
+            parents: HashMap::new(),
+        };
+        let mut seen_globs = HashSet::new();
+
+        // directories come in precedence order, so the first definition wins
+        for dir in dirs {
+            let globs = fs::read_to_string(dir.join("globs2"))
+                .map(|content| (content, true))
+                .or_else(|_| fs::read_to_string(dir.join("globs")).map(|c| (c, false)));
+            if let Ok((content, weighted)) = globs {
+                for line in content.lines().filter(|l| !l.starts_with('#')) {
+                    let fields: Vec<&str> = line.split(':').collect();
+                    let (weight, mime_type, pattern, flags) = match (weighted, fields.as_slice()) {
+                        (true, [weight, mime, pattern, rest @ ..]) => (
+                            weight.parse().unwrap_or(DEFAULT_GLOB_WEIGHT),
+                            *mime,
+                            *pattern,
+                            rest,
+                        ),
+                        (false, [mime, pattern]) => (DEFAULT_GLOB_WEIGHT, *mime, *pattern, &[][..]),
+                        _ => continue,
+                    };
+                    if !seen_globs.insert((mime_type.to_string(), pattern.to_string())) {
+                        continue;
+                    }
+                    let case_sensitive = flags.first().is_some_and(|f| f.contains("cs"));
+                    database.globs.push(GlobRule {
+                        weight,
+                        mime_type: mime_type.to_string(),
+                        pattern: if case_sensitive {
+                            pattern.to_string()
+                        } else {
+                            pattern.to_lowercase()
+                        },
+                        case_sensitive,
+                    });
+                }
+            }
+
+            if let Some(sections) = fs::read(dir.join("magic"))
+                .ok()
+                .and_then(|data| parse_magic(&data))
+            {
+                database.magic.extend(sections);
+            }
+
+            for (file, is_alias) in [("aliases", true), ("subclasses", false)] {
+                let Ok(content) = fs::read_to_string(dir.join(file)) else {
+                    continue;
+                };
+                for (key, value) in content.lines().filter_map(|l| l.split_once(' ')) {
+                    if is_alias {
+                        database
+                            .aliases
+                            .entry(key.to_string())
+                            .or_insert_with(|| value.to_string());
+                    } else {
+                        let parents = database.parents.entry(key.to_string()).or_default();
+                        if !parents.iter().any(|p| p == value) {
+                            parents.push(value.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        database
+            .magic
+            .sort_by_key(|section| std::cmp::Reverse(section.priority));
+        database
+    }
+
+    fn canonical<'a>(&'a self, mime_type: &'a str) -> &'a str {
+        self.aliases
+            .get(mime_type)
+            .map(String::as_str)
+            .unwrap_or(mime_type)
+    }
+
+    /// Returns the types whose glob matches `file_name`, keeping only the
+    /// highest weight and, among those, the longest pattern.
+    fn glob_matches(&self, file_name: &str) -> Vec<String> {
+        let name: Vec<char> = file_name.chars().collect();
+        let lower_name: Vec<char> = file_name.to_lowercase().chars().collect();
+
+        let mut best: Option<(u32, usize)> = None;
+        let mut types: Vec<String> = Vec::new();
+        for rule in &self.globs {
+            let pattern: Vec<char> = rule.pattern.chars().collect();
+            let candidate = if rule.case_sensitive {
+                &name
+            } else {
+                &lower_name
+            };
+            if !glob_match(&pattern, candidate) {
+                continue;
+            }
+
+            let rank = (rule.weight, pattern.len());
+            if best < Some(rank) {
+                best = Some(rank);
+                types.clear();
+            }
+            if best == Some(rank) && !types.contains(&rule.mime_type) {
+                types.push(rule.mime_type.clone());
+            }
+        }
+        types
+    }
+
+    fn magic_match(&self, data: &[u8]) -> Option<&str> {
+        self.magic
+            .iter()
+            .find(|section| section.matches(data))
+            .map(|section| section.mime_type.as_str())
+    }
+
+    fn magic_bytes_needed(&self) -> usize {
+        self.magic
+            .iter()
+            .flat_map(|section| section.rules.iter())
+            .map(MagicRule::bytes_needed)
+            .max()
+            .unwrap_or(0)
+            .min(MAX_MAGIC_BYTES)
+    }
+
+    /// Returns `mime_type` followed by all of its ancestors, nearest first.
+    fn hierarchy(&self, mime_type: &str) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut queue = VecDeque::from([self.canonical(mime_type).to_string()]);
+        while let Some(current) = queue.pop_front() {
+            if result.contains(&current) {
+                continue;
+            }
+            if let Some(parents) = self.parents.get(&current) {
+                queue.extend(parents.iter().cloned());
+            }
+            // every text format can be shown as plain text
+            if current.starts_with("text/") && current != "text/plain" {
+                queue.push_back("text/plain".to_string());
+            }
+            result.push(current);
+        }
+        result
+    }
+
+    /// Determines the MIME type of `path` from its name and, when that is
+    /// ambiguous or unknown, its contents.
+    fn type_of(&self, path: &Path) -> String {
+        if path.is_dir() {
+            return "inode/directory".to_string();
+        }
+
+        let glob_types = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| self.glob_matches(name))
+            .unwrap_or_default();
+        if glob_types.len() == 1 {
+            return self.canonical(&glob_types[0]).to_string();
+        }
+
+        let mut data = Vec::new();
+        if let Ok(file) = fs::File::open(path) {
+            let _ = file
+                .take(self.magic_bytes_needed().max(512) as u64)
+                .read_to_end(&mut data);
+        }
+
+        if let Some(magic_type) = self.magic_match(&data) {
+            if glob_types.is_empty() || glob_types.iter().any(|t| t == magic_type) {
+                return self.canonical(magic_type).to_string();
+            }
+        }
+        if let Some(glob_type) = glob_types.first() {
+            return self.canonical(glob_type).to_string();
+        }
+
+        if looks_like_text(&data) {
+            "text/plain".to_string()
+        } else {
+            "application/octet-stream".to_string()
+        }
+    }
+}
+
+fn looks_like_text(data: &[u8]) -> bool {
+    !data.contains(&0)
+        && match std::str::from_utf8(data) {
+            Ok(_) => true,
+            // the sample may end in the middle of a character
+            Err(e) => e.error_len().is_none(),
+        }
+}
+
+/// Determines the MIME type of `path`.
+pub fn mime_type_of(path: &Path) -> String {
+    MIME_DATABASE.type_of(path)
+}
+
+/// One `mimeapps.list` file, with the desktop-file IDs listed per MIME type.
+#[derive(Default)]
+struct MimeAppsList {
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+fn config_directories() -> Vec<PathBuf> {
+    config_directories_from(
+        env::var("XDG_CONFIG_HOME").ok().as_deref(),
+        env::var("HOME").ok().as_deref(),
+        env::var("XDG_CONFIG_DIRS").ok().as_deref(),
+    )
+}
+
+/// `$XDG_CONFIG_HOME` followed by `$XDG_CONFIG_DIRS`, with the spec's
+/// defaults for unset or empty variables.
+fn config_directories_from(
+    xdg_config_home: Option<&str>,
+    home: Option<&str>,
+    xdg_config_dirs: Option<&str>,
+) -> Vec<PathBuf> {
+    let config_home = xdg_config_home
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|h| PathBuf::from(h).join(".config")));
+    let config_dirs = xdg_config_dirs
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/etc/xdg");
+
+    config_home
+        .into_iter()
+        .chain(
+            config_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .collect()
+}
+
+/// Returns the `mimeapps.list` files in precedence order: config directories
+/// before data directories, and desktop-specific lists before generic ones.
+fn mimeapps_list_paths() -> Vec<PathBuf> {
+    let dirs = config_directories().into_iter().chain(
+        DesktopFileManager::get_data_directories()
+            .into_iter()
+            .map(|dir| dir.join("applications")),
+    );
+    mimeapps_lists_in(dirs, &DesktopFileManager::current_desktops())
+}
+
+fn mimeapps_lists_in(dirs: impl Iterator<Item = PathBuf>, desktops: &[String]) -> Vec<PathBuf> {
+    let desktops: Vec<String> = desktops
+        .iter()
+        .map(|desktop| desktop.to_lowercase())
+        .collect();
+
+    dirs.flat_map(|dir| {
+        desktops
+            .iter()
+            .map(|desktop| dir.join(format!("{}-mimeapps.list", desktop)))
+            .chain(std::iter::once(dir.join("mimeapps.list")))
+            .collect::<Vec<_>>()
+    })
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+fn read_mimeapps_list(path: &Path) -> MimeAppsList {
+    let Ok(content) = fs::read_to_string(path) else {
+        return MimeAppsList::default();
+    };
+    let mut sections = parse_ini(&content);
+    let mut take = |name: &str| -> HashMap<String, Vec<String>> {
+        sections
+            .remove(name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(mime_type, ids)| {
+                let ids = ids
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(String::from)
+                    .collect();
+                (mime_type, ids)
+            })
+            .collect()
+    };
+
+    MimeAppsList {
+        defaults: take("Default Applications"),
+        added: take("Added Associations"),
+        removed: take("Removed Associations"),
+    }
+}
+
+/// Resolves which installed apps handle which MIME types, following the XDG
+/// MIME Applications spec.
+pub struct MimeAssociations<'a> {
+    apps: &'a [App],
+    lists: Vec<MimeAppsList>,
+    database: &'a MimeDatabase,
+}
+
+impl<'a> MimeAssociations<'a> {
+    pub fn new(apps: &'a [App]) -> Self {
+        Self::from_lists(apps, &mimeapps_list_paths(), &MIME_DATABASE)
+    }
+
+    fn from_lists(apps: &'a [App], list_paths: &[PathBuf], database: &'a MimeDatabase) -> Self {
+        Self {
+            apps,
+            lists: list_paths
+                .iter()
+                .map(|path| read_mimeapps_list(path))
+                .collect(),
+            database,
+        }
+    }
+
+    fn find_app(&self, id: &str) -> Option<&'a App> {
+        self.apps
+            .iter()
+            .find(|app| app.desktop_file_id.as_deref() == Some(id))
+    }
+
+    /// Apps associated with exactly `mime_type`, most preferred first.
+    fn apps_for_type(&self, mime_type: &str) -> Vec<&'a App> {
+        let database = self.database;
+        let mut removed: HashSet<&str> = HashSet::new();
+        let mut ids: Vec<&str> = Vec::new();
+
+        // an association removed in one file only hides those of lower precedence
+        for list in &self.lists {
+            for id in list.added.get(mime_type).into_iter().flatten() {
+                if !removed.contains(id.as_str()) && !ids.contains(&id.as_str()) {
+                    ids.push(id);
+                }
+            }
+            removed.extend(
+                list.removed
+                    .get(mime_type)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            );
+        }
+
+        let mut result: Vec<&'a App> = ids.iter().filter_map(|id| self.find_app(id)).collect();
+        for app in self.apps {
+            let Some(id) = app.desktop_file_id.as_deref() else {
+                continue;
+            };
+            let supports_type = app
+                .mime_types
+                .iter()
+                .any(|t| database.canonical(t) == mime_type);
+            if supports_type && !removed.contains(id) && !ids.contains(&id) {
+                result.push(app);
+            }
+        }
+        result
+    }
+
+    fn default_for_type(&self, mime_type: &str) -> Option<&'a App> {
+        self.lists
+            .iter()
+            .flat_map(|list| list.defaults.get(mime_type).into_iter().flatten())
+            .find_map(|id| self.find_app(id))
+            .or_else(|| self.apps_for_type(mime_type).into_iter().next())
+    }
+
+    /// Returns the app that opens `mime_type` by default, falling back to the
+    /// handlers of its parent types.
+    pub fn default_app(&self, mime_type: &str) -> Option<&'a App> {
+        self.database
+            .hierarchy(mime_type)
+            .iter()
+            .find_map(|t| self.default_for_type(t))
+    }
+
+    /// Returns every app able to open `mime_type`, the default one first.
+    pub fn all_apps(&self, mime_type: &str) -> Vec<&'a App> {
+        let mut result: Vec<&'a App> = self.default_app(mime_type).into_iter().collect();
+        for t in self.database.hierarchy(mime_type) {
+            for app in self.apps_for_type(&t) {
+                if !result
+                    .iter()
+                    .any(|a| a.desktop_file_id == app.desktop_file_id)
+                {
+                    result.push(app);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory removed when the test ends.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!(
+                "raycast-linux-mime-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, relative: &str, content: impl AsRef<[u8]>) -> PathBuf {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// One rule of a `magic` file: indent, offset, value, mask and range.
+    type Rule<'a> = (u32, usize, &'a [u8], Option<&'a [u8]>, usize);
+
+    /// Encodes sections in the binary format `update-mime-database` writes.
+    fn magic_file(sections: &[(u32, &str, &[Rule])]) -> Vec<u8> {
+        let mut data = b"MIME-Magic\0\n".to_vec();
+        for (priority, mime_type, rules) in sections {
+            data.extend(format!("[{}:{}]\n", priority, mime_type).bytes());
+            for (indent, offset, value, mask, range) in *rules {
+                if *indent > 0 {
+                    data.extend(indent.to_string().bytes());
+                }
+                data.extend(format!(">{}=", offset).bytes());
+                data.extend((value.len() as u16).to_be_bytes());
+                data.extend(*value);
+                if let Some(mask) = mask {
+                    data.push(b'&');
+                    data.extend(*mask);
+                }
+                if *range > 1 {
+                    data.extend(format!("+{}", range).bytes());
+                }
+                data.push(b'\n');
+            }
+        }
+        data
+    }
+
+    fn database(dir: &TestDir, globs2: &str, magic: &[u8]) -> MimeDatabase {
+        dir.write("mime/globs2", globs2);
+        dir.write("mime/magic", magic);
+        MimeDatabase::load_from(&[dir.0.join("mime")])
+    }
+
+    fn globs(database: &MimeDatabase, name: &str) -> Vec<String> {
+        database.glob_matches(name)
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let matches = |pattern: &str, name: &str| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            glob_match(&pattern, &name)
+        };
+        assert!(matches("*.tar.gz", "a.b.tar.gz"));
+        assert!(matches("README*", "README"));
+        assert!(matches("?akefile", "Makefile"));
+        assert!(matches("*.[ch]", "main.h"));
+        assert!(matches("*.[!o]", "main.c"));
+        assert!(!matches("*.[!o]", "main.o"));
+        assert!(matches("core.[0-9]", "core.7"));
+        assert!(matches("*.[", "a.["));
+        assert!(!matches("*.gz", "a.gz.part"));
+    }
+
+    #[test]
+    fn higher_glob_weights_win() {
+        let dir = TestDir::new("weights");
+        let database = database(
+            &dir,
+            "# comment\n\
+             50:application/gzip:*.gz\n\
+             20:application/x-compressed-tar:*.tar.gz\n\
+             80:application/x-low-priority-tar:*.tar\n",
+            b"",
+        );
+        assert_eq!(globs(&database, "a.tar.gz"), vec!["application/gzip"]);
+    }
+
+    #[test]
+    fn longest_glob_wins_at_equal_weight() {
+        let dir = TestDir::new("longest");
+        let database = database(
+            &dir,
+            "50:application/gzip:*.gz\n\
+             50:application/x-compressed-tar:*.tar.gz\n",
+            b"",
+        );
+        assert_eq!(
+            globs(&database, "a.tar.gz"),
+            vec!["application/x-compressed-tar"]
+        );
+        assert_eq!(globs(&database, "a.gz"), vec!["application/gzip"]);
+    }
+
+    #[test]
+    fn globs_ignore_case_unless_marked_case_sensitive() {
+        let dir = TestDir::new("case");
+        let database = database(
+            &dir,
+            "50:text/x-csrc:*.c\n\
+             50:text/x-c++src:*.C:cs\n\
+             50:text/plain:*.TXT\n",
+            b"",
+        );
+        assert_eq!(globs(&database, "README.txt"), vec!["text/plain"]);
+        assert_eq!(globs(&database, "MAIN.c"), vec!["text/x-csrc"]);
+        // both match, so the contents have to decide
+        assert_eq!(
+            globs(&database, "main.C"),
+            vec!["text/x-csrc", "text/x-c++src"]
+        );
+    }
+
+    #[test]
+    fn earlier_directories_define_globs_first() {
+        let dir = TestDir::new("glob-precedence");
+        dir.write("user/globs2", "60:text/x-user:*.foo\n");
+        dir.write(
+            "system/globs2",
+            "90:text/x-user:*.foo\n70:text/x-system:*.foo\n",
+        );
+        dir.write("legacy/globs", "text/x-legacy:*.bar\n");
+        let database = MimeDatabase::load_from(&[
+            dir.0.join("user"),
+            dir.0.join("system"),
+            dir.0.join("legacy"),
+        ]);
+        assert_eq!(globs(&database, "a.foo"), vec!["text/x-system"]);
+        assert_eq!(globs(&database, "a.bar"), vec!["text/x-legacy"]);
+    }
+
+    #[test]
+    fn magic_priority_decides_between_matches() {
+        let dir = TestDir::new("priority");
+        let magic = magic_file(&[
+            (50, "image/x-generic", &[(0, 0, b"\x89", None, 1)]),
+            (80, "image/x-specific", &[(0, 0, b"\x89IMG", None, 1)]),
+        ]);
+        let database = database(&dir, "", &magic);
+        assert_eq!(
+            database.magic_match(b"\x89IMG data"),
+            Some("image/x-specific")
+        );
+        assert_eq!(database.magic_match(b"\x89other"), Some("image/x-generic"));
+        assert_eq!(database.magic_match(b"plain"), None);
+    }
+
+    #[test]
+    fn nested_magic_rules_need_a_matching_child() {
+        let dir = TestDir::new("nested");
+        let magic = magic_file(&[(
+            60,
+            "application/x-container",
+            &[
+                (0, 0, b"PK", None, 1),
+                (1, 30, b"mimetype", None, 1),
+                (1, 30, b"manifest", None, 1),
+                (0, 0, b"ZIP!", None, 1),
+            ],
+        )]);
+        let database = database(&dir, "", &magic);
+
+        let mut data = b"PK".to_vec();
+        data.resize(30, 0);
+        data.extend(b"manifest");
+        assert_eq!(database.magic_match(&data), Some("application/x-container"));
+        // a parent rule with children doesn't match on its own
+        assert_eq!(database.magic_match(b"PK\0\0"), None);
+        assert_eq!(
+            database.magic_match(b"ZIP!"),
+            Some("application/x-container")
+        );
+    }
+
+    #[test]
+    fn magic_ranges_and_masks() {
+        let dir = TestDir::new("ranges");
+        let magic = magic_file(&[
+            (50, "application/x-ranged", &[(0, 4, b"RNG", None, 8)]),
+            (
+                50,
+                "application/x-masked",
+                &[(0, 0, b"\x40\x00", Some(b"\xf0\xff"), 1)],
+            ),
+        ]);
+        let database = database(&dir, "", &magic);
+        assert_eq!(
+            database.magic_match(b"........RNG"),
+            Some("application/x-ranged")
+        );
+        assert_eq!(database.magic_match(b"............RNG"), None);
+        assert_eq!(
+            database.magic_match(b"\x4f\x00"),
+            Some("application/x-masked")
+        );
+        assert_eq!(database.magic_match(b"\x4f\x01"), None);
+        assert_eq!(database.magic_bytes_needed(), 4 + 8 + 3);
+    }
+
+    #[test]
+    fn type_of_combines_globs_and_magic() {
+        let dir = TestDir::new("type-of");
+        let magic = magic_file(&[
+            (
+                50,
+                "text/x-c++src",
+                &[(0, 0, b"#include <iostream>", None, 1)],
+            ),
+            (50, "image/png", &[(0, 0, b"\x89PNG", None, 1)]),
+        ]);
+        dir.write(
+            "mime/aliases",
+            "application/x-old-name application/x-new-name\n",
+        );
+        let database = database(
+            &dir,
+            "50:text/x-csrc:*.c\n50:text/x-c++src:*.C:cs\n50:application/x-old-name:*.old\n",
+            &magic,
+        );
+
+        let file = |name: &str, content: &[u8]| dir.write(&format!("files/{}", name), content);
+        // an unambiguous glob is trusted without reading the file
+        assert_eq!(
+            database.type_of(&file("photo.c", b"\x89PNG")),
+            "text/x-csrc"
+        );
+        // ambiguous globs are settled by magic, or the first glob
+        assert_eq!(
+            database.type_of(&file("main.C", b"#include <iostream>\n")),
+            "text/x-c++src"
+        );
+        assert_eq!(database.type_of(&file("other.C", b"int x;")), "text/x-csrc");
+        // no glob at all
+        assert_eq!(
+            database.type_of(&file("photo", b"\x89PNG....")),
+            "image/png"
+        );
+        assert_eq!(database.type_of(&file("notes", b"hello")), "text/plain");
+        assert_eq!(
+            database.type_of(&file("blob", b"\0\x01\x02")),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            database.type_of(&file("a.old", b"")),
+            "application/x-new-name"
+        );
+        assert_eq!(database.type_of(&dir.0), "inode/directory");
+    }
+
+    #[test]
+    fn hierarchy_follows_subclasses_and_text() {
+        let dir = TestDir::new("hierarchy");
+        dir.write(
+            "mime/subclasses",
+            "application/x-child application/x-parent\n\
+             application/x-parent application/x-grandparent\n\
+             text/x-script application/x-executable\n",
+        );
+        dir.write("mime/aliases", "application/x-kid application/x-child\n");
+        let database = MimeDatabase::load_from(&[dir.0.join("mime")]);
+        assert_eq!(
+            database.hierarchy("application/x-kid"),
+            vec![
+                "application/x-child",
+                "application/x-parent",
+                "application/x-grandparent",
+            ]
+        );
+        assert_eq!(
+            database.hierarchy("text/x-script"),
+            vec!["text/x-script", "application/x-executable", "text/plain"]
+        );
+    }
+
+    #[test]
+    fn config_directories_follow_the_base_directory_spec() {
+        assert_eq!(
+            config_directories_from(Some("/cfg"), Some("/home/u"), Some("/a:/b")),
+            vec![
+                PathBuf::from("/cfg"),
+                PathBuf::from("/a"),
+                PathBuf::from("/b")
+            ]
+        );
+        assert_eq!(
+            config_directories_from(Some(""), Some("/home/u"), None),
+            vec![PathBuf::from("/home/u/.config"), PathBuf::from("/etc/xdg")]
+        );
+    }
+
+    #[test]
+    fn desktop_specific_lists_come_first_in_each_directory() {
+        let dir = TestDir::new("lists");
+        let config_home = dir.write("config/mimeapps.list", "");
+        let kde_config_home = dir.write("config/kde-mimeapps.list", "");
+        // lists for other desktops are ignored
+        dir.write("config/gnome-mimeapps.list", "");
+        let system = dir.write("xdg/mimeapps.list", "");
+        let data = dir.write("data/applications/mimeapps.list", "");
+        let kde_data = dir.write("data/applications/kde-mimeapps.list", "");
+
+        let dirs = ["config", "xdg", "data/applications"]
+            .iter()
+            .map(|relative| dir.0.join(relative));
+        assert_eq!(
+            mimeapps_lists_in(dirs, &["KDE".to_string()]),
+            vec![kde_config_home, config_home, system, kde_data, data]
+        );
+    }
+
+    fn app(id: &str, mime_types: &[&str]) -> App {
+        let mut app = App::new(id.to_string());
+        app.desktop_file_id = Some(id.to_string());
+        app.mime_types = mime_types.iter().map(|t| t.to_string()).collect();
+        app
+    }
+
+    fn ids<'a>(apps: impl IntoIterator<Item = &'a App>) -> Vec<&'a str> {
+        apps.into_iter()
+            .filter_map(|app| app.desktop_file_id.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn mimeapps_lists_apply_in_precedence_order() {
+        const TYPE: &str = "application/x-document";
+        let dir = TestDir::new("associations");
+        let user = dir.write(
+            "config/mimeapps.list",
+            "[Default Applications]\n\
+             application/x-document=missing.desktop;viewer.desktop\n\
+             [Added Associations]\n\
+             application/x-document=added.desktop;\n\
+             [Removed Associations]\n\
+             application/x-document=editor.desktop\n",
+        );
+        let desktop = dir.write(
+            "config/kde-mimeapps.list",
+            "[Default Applications]\n\
+             application/x-document=reader.desktop\n",
+        );
+        let system = dir.write(
+            "xdg/mimeapps.list",
+            "[Default Applications]\n\
+             application/x-document=editor.desktop\n\
+             [Added Associations]\n\
+             application/x-document=editor.desktop;system.desktop\n\
+             [Removed Associations]\n\
+             application/x-document=added.desktop;viewer.desktop\n",
+        );
+        let apps = vec![
+            app("viewer.desktop", &[TYPE]),
+            app("reader.desktop", &[]),
+            app("editor.desktop", &[TYPE]),
+            app("added.desktop", &[]),
+            app("system.desktop", &[]),
+            app("unrelated.desktop", &["text/plain"]),
+        ];
+        let database = MimeDatabase::load_from(&[]);
+
+        let associations = MimeAssociations::from_lists(
+            &apps,
+            &[desktop.clone(), user.clone(), system.clone()],
+            &database,
+        );
+        // the desktop-specific default comes before the generic one
+        assert_eq!(ids(associations.default_app(TYPE)), vec!["reader.desktop"]);
+        // a removal hides associations of lower precedence, including the
+        // types apps declare themselves, but not ones added above it
+        assert_eq!(
+            ids(associations.all_apps(TYPE)),
+            vec!["reader.desktop", "added.desktop", "system.desktop"]
+        );
+
+        // defaults skip apps that aren't installed
+        let associations = MimeAssociations::from_lists(&apps, &[user, system], &database);
+        assert_eq!(ids(associations.default_app(TYPE)), vec!["viewer.desktop"]);
+
+        // without defaults, the most preferred association is used
+        let added = dir.write(
+            "data/applications/mimeapps.list",
+            "[Added Associations]\n\
+             application/x-document=system.desktop\n",
+        );
+        let associations = MimeAssociations::from_lists(&apps, &[added], &database);
+        assert_eq!(ids(associations.default_app(TYPE)), vec!["system.desktop"]);
+        let associations = MimeAssociations::from_lists(&apps, &[], &database);
+        assert_eq!(
+            ids(associations.all_apps(TYPE)),
+            vec!["viewer.desktop", "editor.desktop"]
+        );
+    }
+
+    #[test]
+    fn parent_types_provide_fallback_handlers() {
+        let dir = TestDir::new("fallback");
+        dir.write("mime/subclasses", "text/x-log text/x-generic-log\n");
+        let database = MimeDatabase::load_from(&[dir.0.join("mime")]);
+        let apps = vec![
+            app("logs.desktop", &["text/x-generic-log"]),
+            app("editor.desktop", &["text/plain"]),
+        ];
+        let associations = MimeAssociations::from_lists(&apps, &[], &database);
+        assert_eq!(
+            ids(associations.default_app("text/x-log")),
+            vec!["logs.desktop"]
+        );
+        assert_eq!(
+            ids(associations.all_apps("text/x-log")),
+            vec!["logs.desktop", "editor.desktop"]
+        );
+    }
+}
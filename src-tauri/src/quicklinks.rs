@@ -1,9 +1,17 @@
+use crate::clipboard_history;
 use crate::error::AppError;
+use crate::favicon::{CachedFavicon, FaviconCache};
+use crate::migrations::{self, Migration};
+use crate::snippets::engine::{self, Argument, PlaceholderValues};
+use crate::snippets::manager::SnippetManager;
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, Result as RusqliteResult};
-use serde::Serialize;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_opener::{open_path, open_url};
 
 #[derive(Serialize, Clone, Debug)]
@@ -16,10 +24,187 @@ pub struct Quicklink {
     icon: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
+    /// Typing the alias exactly brings the quicklink up first.
+    alias: Option<String>,
+    tags: Vec<String>,
+    times_used: i64,
+    last_used_at: DateTime<Utc>,
+}
+
+/// A quicklink in the JSON format Raycast imports and exports.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RaycastQuicklink {
+    name: String,
+    link: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_with: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    quicklinks_added: u32,
+    duplicates_skipped: u32,
+    invalid_skipped: u32,
+}
+
+// what encodeURIComponent leaves alone
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration::sql(
+        "create quicklinks table",
+        "CREATE TABLE IF NOT EXISTS quicklinks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            link TEXT NOT NULL,
+            application TEXT,
+            icon TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+    ),
+    Migration::sql(
+        "add aliases, tags and usage",
+        "ALTER TABLE quicklinks ADD COLUMN alias TEXT;
+        ALTER TABLE quicklinks ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+        ALTER TABLE quicklinks ADD COLUMN times_used INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE quicklinks ADD COLUMN last_used_at INTEGER NOT NULL DEFAULT 0;
+        CREATE UNIQUE INDEX quicklinks_alias ON quicklinks (alias COLLATE NOCASE);",
+    ),
+];
+
+const QUICKLINK_COLUMNS: &str = "id, name, link, application, icon, created_at, updated_at, alias, tags, times_used, last_used_at";
+
+fn row_to_quicklink(row: &rusqlite::Row) -> rusqlite::Result<Quicklink> {
+    let created_at_ts: i64 = row.get(5)?;
+    let updated_at_ts: i64 = row.get(6)?;
+    let tags: String = row.get(8)?;
+    let last_used_at_ts: i64 = row.get(10)?;
+    Ok(Quicklink {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        link: row.get(2)?,
+        application: row.get(3)?,
+        icon: row.get(4)?,
+        created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
+        alias: row.get(7)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        times_used: row.get(9)?,
+        last_used_at: DateTime::from_timestamp(last_used_at_ts, 0).unwrap_or_default(),
+    })
+}
+
+fn normalize_alias(alias: Option<String>) -> Option<String> {
+    alias
+        .map(|alias| alias.trim().to_string())
+        .filter(|alias| !alias.is_empty())
+}
+
+fn normalize_tags(tags: Vec<String>) -> String {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    serde_json::to_string(&normalized).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// A LIKE pattern matching text that contains `term`, with `%` and `_` in
+/// it taken literally.
+fn contains_pattern(term: &str) -> String {
+    let escaped = term
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Fails if another quicklink than `id` already uses the alias.
+fn check_alias_free(db: &Connection, alias: Option<&str>, id: Option<i64>) -> Result<(), AppError> {
+    let Some(alias) = alias else {
+        return Ok(());
+    };
+    let owner: Option<String> = db
+        .query_row(
+            "SELECT name FROM quicklinks WHERE alias = ?1 COLLATE NOCASE AND id IS NOT ?2",
+            params![alias, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match owner {
+        Some(name) => Err(AppError::Quicklink(format!(
+            "The alias \"{}\" is already used by {}",
+            alias, name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether the link is a URL rather than a path. `file:` URLs count as
+/// paths, and so do Windows drive letters.
+fn is_url(link: &str) -> bool {
+    url::Url::parse(link).is_ok_and(|url| url.scheme().len() > 1 && url.scheme() != "file")
+}
+
+fn url_encode(value: &str) -> String {
+    utf8_percent_encode(value, URL_COMPONENT).to_string()
+}
+
+/// Keeps a value inside the path segment it is substituted into. Paths are
+/// opened directly, never through a shell, so nothing else needs escaping.
+fn path_segment(value: &str) -> String {
+    let value: String = value.chars().filter(|&c| c != '/' && c != '\0').collect();
+    if value == "." || value == ".." {
+        String::new()
+    } else {
+        value
+    }
+}
+
+/// Fills in the link's placeholders. Values are URL-encoded for URLs and
+/// can't leave their path segment for paths, so an argument can't change the
+/// link's shape.
+fn resolve_link(
+    app: &AppHandle,
+    link: &str,
+    arguments: HashMap<String, String>,
+) -> Result<String, AppError> {
+    let escape: fn(&str) -> String = if is_url(link) {
+        url_encode
+    } else {
+        path_segment
+    };
+    let values = PlaceholderValues {
+        arguments,
+        selection: engine::contains_placeholder(link, "selection").then(selection::get_text),
+        escape: Some(escape),
+    };
+    let snippet_manager = app.state::<SnippetManager>();
+    let clipboard_manager = clipboard_history::manager::MANAGER.lock().unwrap();
+    let resolved =
+        engine::resolve_placeholders(link, &snippet_manager, clipboard_manager.as_ref(), &values)?;
+    Ok(resolved.content)
 }
 
 pub struct QuicklinkManager {
     db: Mutex<Connection>,
+    favicons: FaviconCache,
 }
 
 impl QuicklinkManager {
@@ -29,26 +214,19 @@ impl QuicklinkManager {
             .app_local_data_dir()
             .map_err(|_| AppError::DirectoryNotFound)?;
         let db_path = data_dir.join("quicklinks.sqlite");
-        let db = Connection::open(db_path)?;
+        Self::with_connection(Connection::open(db_path)?, data_dir.join("favicons"))
+    }
 
-        Ok(Self { db: Mutex::new(db) })
+    fn with_connection(db: Connection, favicons_dir: PathBuf) -> Result<Self, AppError> {
+        Ok(Self {
+            db: Mutex::new(db),
+            favicons: FaviconCache::new(favicons_dir)?,
+        })
     }
 
-    pub fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS quicklinks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                link TEXT NOT NULL,
-                application TEXT,
-                icon TEXT,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
-        Ok(())
+    pub fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)
     }
 
     fn create_quicklink(
@@ -57,39 +235,78 @@ impl QuicklinkManager {
         link: String,
         application: Option<String>,
         icon: Option<String>,
+        alias: Option<String>,
+        tags: Vec<String>,
     ) -> Result<i64, AppError> {
         let db = self.db.lock().unwrap();
+        let alias = normalize_alias(alias);
+        check_alias_free(&db, alias.as_deref(), None)?;
         let now = Utc::now().timestamp();
         db.execute(
-            "INSERT INTO quicklinks (name, link, application, icon, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?)",
-            params![name, link, application, icon, now, now],
+            "INSERT INTO quicklinks (name, link, application, icon, alias, tags, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![name, link, application, icon, alias, normalize_tags(tags), now, now],
         )?;
         Ok(db.last_insert_rowid())
     }
 
-    fn list_quicklinks(&self) -> Result<Vec<Quicklink>, AppError> {
+    /// Lists quicklinks whose name, link, alias or tags contain the search
+    /// term. An exact alias match comes first, then the most used.
+    fn list_quicklinks(&self, search_term: Option<String>) -> Result<Vec<Quicklink>, AppError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare("SELECT id, name, link, application, icon, created_at, updated_at FROM quicklinks ORDER BY name ASC")?;
-        let quicklinks_iter = stmt.query_map([], |row| {
-            let created_at_ts: i64 = row.get(5)?;
-            let updated_at_ts: i64 = row.get(6)?;
-            Ok(Quicklink {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                link: row.get(2)?,
-                application: row.get(3)?,
-                icon: row.get(4)?,
-                created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
-                updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
-            })
-        })?;
+        let mut query = format!("SELECT {} FROM quicklinks", QUICKLINK_COLUMNS);
+        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+
+        match search_term.filter(|term| !term.trim().is_empty()) {
+            Some(term) => {
+                query.push_str(
+                    " WHERE name LIKE ?1 ESCAPE '\\' OR link LIKE ?1 ESCAPE '\\'
+                        OR alias LIKE ?1 ESCAPE '\\'
+                        OR EXISTS (SELECT 1 FROM json_each(tags) WHERE value LIKE ?1 ESCAPE '\\')
+                     ORDER BY alias = ?2 COLLATE NOCASE DESC, times_used DESC, name ASC",
+                );
+                params_vec.push(Box::new(contains_pattern(term.trim())));
+                params_vec.push(Box::new(term.trim().to_string()));
+            }
+            None => query.push_str(" ORDER BY times_used DESC, name ASC"),
+        }
+
+        let params_ref: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
+
+        let mut stmt = db.prepare(&query)?;
+        let quicklinks_iter = stmt.query_map(&params_ref[..], row_to_quicklink)?;
 
         quicklinks_iter
             .collect::<Result<Vec<_>, _>>()
             .map_err(|e| e.into())
     }
 
+    fn get_quicklink(&self, id: i64) -> Result<Option<Quicklink>, AppError> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!("SELECT {} FROM quicklinks WHERE id = ?", QUICKLINK_COLUMNS),
+            params![id],
+            row_to_quicklink,
+        )
+        .optional()
+        .map_err(|e| e.into())
+    }
+
+    fn find_quicklink_by_alias(&self, alias: &str) -> Result<Option<Quicklink>, AppError> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {} FROM quicklinks WHERE alias = ?1 COLLATE NOCASE",
+                QUICKLINK_COLUMNS
+            ),
+            params![alias.trim()],
+            row_to_quicklink,
+        )
+        .optional()
+        .map_err(|e| e.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn update_quicklink(
         &self,
         id: i64,
@@ -97,17 +314,157 @@ impl QuicklinkManager {
         link: String,
         application: Option<String>,
         icon: Option<String>,
+        alias: Option<String>,
+        tags: Vec<String>,
     ) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
+        let alias = normalize_alias(alias);
+        check_alias_free(&db, alias.as_deref(), Some(id))?;
         let now = Utc::now().timestamp();
         db.execute(
-            "UPDATE quicklinks SET name = ?, link = ?, application = ?, icon = ?, updated_at = ?
-             WHERE id = ?",
-            params![name, link, application, icon, now, id],
+            "UPDATE quicklinks SET name = ?, link = ?, application = ?, icon = ?, alias = ?, tags = ?,
+             updated_at = ? WHERE id = ?",
+            params![name, link, application, icon, alias, normalize_tags(tags), now, id],
         )?;
         Ok(())
     }
 
+    fn quicklink_was_used(&self, id: i64) -> Result<(), AppError> {
+        let db = self.db.lock().unwrap();
+        let now = Utc::now().timestamp();
+        db.execute(
+            "UPDATE quicklinks SET times_used = times_used + 1, last_used_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    }
+
+    /// Adds every valid entry that doesn't repeat the name and link of an
+    /// existing quicklink or an earlier entry.
+    fn import_quicklinks(&self, entries: Vec<serde_json::Value>) -> Result<ImportResult, AppError> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let mut seen: HashSet<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT name, link FROM quicklinks")?;
+            let existing = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            existing
+        };
+        let now = Utc::now().timestamp();
+        let mut result = ImportResult::default();
+
+        for entry in entries {
+            let Ok(quicklink) = serde_json::from_value::<RaycastQuicklink>(entry) else {
+                result.invalid_skipped += 1;
+                continue;
+            };
+            let name = quicklink.name.trim().to_string();
+            let link = quicklink.link.trim().to_string();
+            if name.is_empty() || link.is_empty() {
+                result.invalid_skipped += 1;
+                continue;
+            }
+            if !seen.insert((name.clone(), link.clone())) {
+                result.duplicates_skipped += 1;
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO quicklinks (name, link, application, icon, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![name, link, quicklink.open_with, quicklink.icon, now, now],
+            )?;
+            result.quicklinks_added += 1;
+        }
+
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Every quicklink in Raycast's format.
+    fn export_quicklinks(&self) -> Result<Vec<RaycastQuicklink>, AppError> {
+        // cached favicons are local files that mean nothing on another machine
+        let favicon_prefix = self.favicon_prefix();
+        Ok(self
+            .list_quicklinks(None)?
+            .into_iter()
+            .map(|quicklink| RaycastQuicklink {
+                name: quicklink.name,
+                link: quicklink.link,
+                open_with: quicklink.application,
+                icon: quicklink
+                    .icon
+                    .filter(|icon| !icon.starts_with(&favicon_prefix)),
+            })
+            .collect())
+    }
+
+    fn favicon_prefix(&self) -> String {
+        self.favicons.dir().join("").to_string_lossy().into_owned()
+    }
+
+    /// URL quicklinks, all of them or just `id`, that have no icon or one
+    /// from the favicon cache. A chosen icon is never replaced.
+    fn favicon_candidates(&self, id: Option<i64>) -> Result<Vec<(i64, String)>, AppError> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT id, link FROM quicklinks
+             WHERE (?1 IS NULL OR id = ?1) AND (icon IS NULL OR instr(icon, ?2) = 1)",
+        )?;
+        let candidates = stmt
+            .query_map(params![id, self.favicon_prefix()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<(i64, String)>, _>>()?;
+        Ok(candidates
+            .into_iter()
+            .filter(|(_, link)| is_url(link))
+            .collect())
+    }
+
+    /// Points the quicklink at its favicon and forgets icons the cache
+    /// evicted. Returns how many quicklinks changed.
+    fn set_favicon(&self, id: i64, link: &str, favicon: &CachedFavicon) -> Result<usize, AppError> {
+        let db = self.db.lock().unwrap();
+        let mut changed = 0;
+        for path in &favicon.evicted {
+            changed += db.execute(
+                "UPDATE quicklinks SET icon = NULL WHERE icon = ?1",
+                params![path.to_string_lossy()],
+            )?;
+        }
+        // the quicklink may have been edited while the icon was downloading
+        changed += db.execute(
+            "UPDATE quicklinks SET icon = ?1
+             WHERE id = ?2 AND link = ?3 AND (icon IS NULL OR instr(icon, ?4) = 1)",
+            params![
+                favicon.path.to_string_lossy(),
+                id,
+                link,
+                self.favicon_prefix()
+            ],
+        )?;
+        Ok(changed)
+    }
+
+    /// Fetches favicons for [`Self::favicon_candidates`]. With `refresh`,
+    /// each site's icon is downloaded again instead of read from the cache.
+    async fn fetch_favicons(&self, id: Option<i64>, refresh: bool) -> Result<usize, AppError> {
+        let mut refreshed_hosts = HashSet::new();
+        let mut changed = 0;
+        for (id, link) in self.favicon_candidates(id)? {
+            let host = url::Url::parse(&link)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string));
+            let refresh = refresh && host.is_some_and(|host| refreshed_hosts.insert(host));
+            match self.favicons.fetch(&link, refresh).await {
+                Ok(favicon) => changed += self.set_favicon(id, &link, &favicon)?,
+                Err(e) => eprintln!("Failed to fetch favicon for {}: {}", link, e),
+            }
+        }
+        Ok(changed)
+    }
+
     fn delete_quicklink(&self, id: i64) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
         db.execute("DELETE FROM quicklinks WHERE id = ?", params![id])?;
@@ -115,6 +472,22 @@ impl QuicklinkManager {
     }
 }
 
+/// Fetches missing favicons off the command's thread and tells the frontend
+/// to reload quicklinks if any icon changed.
+fn fetch_favicons_in_background(app: &AppHandle, id: Option<i64>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let manager = app.state::<QuicklinkManager>();
+        match manager.fetch_favicons(id, false).await {
+            Ok(0) => {}
+            Ok(_) => {
+                let _ = app.emit("quicklinks-changed", ());
+            }
+            Err(e) => eprintln!("Failed to fetch quicklink favicons: {}", e),
+        }
+    });
+}
+
 #[tauri::command]
 pub fn create_quicklink(
     app: AppHandle,
@@ -122,20 +495,50 @@ pub fn create_quicklink(
     link: String,
     application: Option<String>,
     icon: Option<String>,
+    alias: Option<String>,
+    tags: Option<Vec<String>>,
 ) -> Result<i64, String> {
+    let id = app
+        .state::<QuicklinkManager>()
+        .create_quicklink(
+            name,
+            link,
+            application,
+            icon,
+            alias,
+            tags.unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())?;
+    fetch_favicons_in_background(&app, Some(id));
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn list_quicklinks(
+    app: AppHandle,
+    search_term: Option<String>,
+) -> Result<Vec<Quicklink>, String> {
+    app.state::<QuicklinkManager>()
+        .list_quicklinks(search_term)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_quicklink_by_alias(app: AppHandle, alias: String) -> Result<Option<Quicklink>, String> {
     app.state::<QuicklinkManager>()
-        .create_quicklink(name, link, application, icon)
+        .find_quicklink_by_alias(&alias)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn list_quicklinks(app: AppHandle) -> Result<Vec<Quicklink>, String> {
+pub fn quicklink_was_used(app: AppHandle, id: i64) -> Result<(), String> {
     app.state::<QuicklinkManager>()
-        .list_quicklinks()
+        .quicklink_was_used(id)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn update_quicklink(
     app: AppHandle,
     id: i64,
@@ -143,10 +546,22 @@ pub fn update_quicklink(
     link: String,
     application: Option<String>,
     icon: Option<String>,
+    alias: Option<String>,
+    tags: Option<Vec<String>>,
 ) -> Result<(), String> {
     app.state::<QuicklinkManager>()
-        .update_quicklink(id, name, link, application, icon)
-        .map_err(|e| e.to_string())
+        .update_quicklink(
+            id,
+            name,
+            link,
+            application,
+            icon,
+            alias,
+            tags.unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())?;
+    fetch_favicons_in_background(&app, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
@@ -157,7 +572,64 @@ pub fn delete_quicklink(app: AppHandle, id: i64) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn execute_quicklink(link: String, application: Option<String>) -> Result<(), String> {
+pub fn import_quicklinks(app: AppHandle, json_content: String) -> Result<ImportResult, String> {
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(&json_content).map_err(|e| e.to_string())?;
+    let result = app
+        .state::<QuicklinkManager>()
+        .import_quicklinks(entries)
+        .map_err(|e| e.to_string())?;
+    fetch_favicons_in_background(&app, None);
+    Ok(result)
+}
+
+/// Writes every quicklink to `path` in Raycast's format and returns how many
+/// there were.
+#[tauri::command]
+pub fn export_quicklinks(app: AppHandle, path: String) -> Result<usize, String> {
+    let quicklinks = app
+        .state::<QuicklinkManager>()
+        .export_quicklinks()
+        .map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&quicklinks).map_err(|e| e.to_string())?;
+    std::fs::write(path, content).map_err(|e| e.to_string())?;
+    Ok(quicklinks.len())
+}
+
+/// Downloads favicons again for one quicklink or all of them and returns how
+/// many quicklinks changed icon.
+#[tauri::command]
+pub async fn refresh_quicklink_favicons(app: AppHandle, id: Option<i64>) -> Result<usize, String> {
+    let changed = app
+        .state::<QuicklinkManager>()
+        .fetch_favicons(id, true)
+        .await
+        .map_err(|e| e.to_string())?;
+    if changed > 0 {
+        let _ = app.emit("quicklinks-changed", ());
+    }
+    Ok(changed)
+}
+
+#[tauri::command]
+pub fn get_quicklink_arguments(app: AppHandle, id: i64) -> Result<Vec<Argument>, String> {
+    let quicklink = app
+        .state::<QuicklinkManager>()
+        .get_quicklink(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Quicklink {} not found", id))?;
+    Ok(engine::parse_arguments(&quicklink.link))
+}
+
+#[tauri::command]
+pub fn execute_quicklink(
+    app: AppHandle,
+    link: String,
+    application: Option<String>,
+    arguments: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let link =
+        resolve_link(&app, &link, arguments.unwrap_or_default()).map_err(|e| e.to_string())?;
     if let Some(app_name) = application {
         open_path(link, Some(app_name)).map_err(|e| e.to_string())
     } else if link.starts_with("http://") || link.starts_with("https://") {
@@ -166,3 +638,190 @@ pub fn execute_quicklink(link: String, application: Option<String>) -> Result<()
         open_path(link, None::<String>).map_err(|e| e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn manager() -> QuicklinkManager {
+        let manager = QuicklinkManager::with_connection(
+            Connection::open_in_memory().unwrap(),
+            PathBuf::from("/nonexistent/favicons"),
+        )
+        .unwrap();
+        manager.init_db().unwrap();
+        manager
+    }
+
+    fn names_and_links(manager: &QuicklinkManager) -> Vec<(String, String)> {
+        let mut quicklinks: Vec<_> = manager
+            .list_quicklinks(None)
+            .unwrap()
+            .into_iter()
+            .map(|quicklink| (quicklink.name, quicklink.link))
+            .collect();
+        quicklinks.sort();
+        quicklinks
+    }
+
+    #[test]
+    fn urls_need_a_scheme_other_than_file() {
+        assert!(is_url("https://example.com/search?q={query}"));
+        assert!(is_url("mailto:someone@example.com"));
+        assert!(is_url("raycast://extensions/raycast/github"));
+        assert!(!is_url("file:///home/user/Documents"));
+        assert!(!is_url("/home/user/Documents"));
+        assert!(!is_url("~/Documents"));
+        assert!(!is_url("Documents/notes.txt"));
+    }
+
+    #[test]
+    fn drive_letters_are_not_schemes() {
+        assert!(!is_url("C:\\Users\\me"));
+        assert!(!is_url("c:/Users/me"));
+    }
+
+    #[test]
+    fn path_segments_cant_leave_their_directory() {
+        assert_eq!(path_segment("notes.txt"), "notes.txt");
+        assert_eq!(path_segment("a/b"), "ab");
+        assert_eq!(path_segment("a\0b"), "ab");
+        assert_eq!(path_segment("."), "");
+        assert_eq!(path_segment(".."), "");
+        assert_eq!(path_segment("/../"), "");
+        assert_eq!(path_segment("..."), "...");
+    }
+
+    #[test]
+    fn import_skips_duplicates_and_invalid_entries() {
+        let manager = manager();
+        manager
+            .create_quicklink(
+                "Docs".to_string(),
+                "https://docs.rs".to_string(),
+                None,
+                None,
+                None,
+                Vec::new(),
+            )
+            .unwrap();
+
+        let result = manager
+            .import_quicklinks(vec![
+                json!({ "name": "Docs", "link": "https://docs.rs" }),
+                json!({ "name": " Crates ", "link": "https://crates.io", "openWith": "firefox" }),
+                json!({ "name": "Crates", "link": "https://crates.io " }),
+                json!({ "name": "  ", "link": "https://example.com" }),
+                json!({ "name": "No link" }),
+                json!("https://example.com"),
+            ])
+            .unwrap();
+
+        assert_eq!(result.quicklinks_added, 1);
+        assert_eq!(result.duplicates_skipped, 2);
+        assert_eq!(result.invalid_skipped, 3);
+        assert_eq!(
+            names_and_links(&manager),
+            vec![
+                ("Crates".to_string(), "https://crates.io".to_string()),
+                ("Docs".to_string(), "https://docs.rs".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_quicklinks_import_unchanged() {
+        let exporter = manager();
+        exporter
+            .import_quicklinks(vec![
+                json!({ "name": "Search", "link": "https://duckduckgo.com/?q={query}" }),
+                json!({ "name": "Notes", "link": "~/notes", "openWith": "code", "icon": "note" }),
+            ])
+            .unwrap();
+        let favicon = format!("{}example.png", exporter.favicon_prefix());
+        exporter
+            .create_quicklink(
+                "Example".to_string(),
+                "https://example.com".to_string(),
+                None,
+                Some(favicon),
+                None,
+                Vec::new(),
+            )
+            .unwrap();
+
+        let exported = serde_json::to_value(exporter.export_quicklinks().unwrap()).unwrap();
+        let entries = serde_json::from_value::<Vec<serde_json::Value>>(exported).unwrap();
+        assert!(entries
+            .iter()
+            .all(|entry| entry["name"] != "Example" || entry.get("icon").is_none()));
+
+        let importer = manager();
+        let result = importer.import_quicklinks(entries).unwrap();
+        assert_eq!(result.quicklinks_added, 3);
+        assert_eq!(names_and_links(&importer), names_and_links(&exporter));
+        let notes = importer
+            .list_quicklinks(Some("Notes".to_string()))
+            .unwrap()
+            .remove(0);
+        assert_eq!(notes.application.as_deref(), Some("code"));
+        assert_eq!(notes.icon.as_deref(), Some("note"));
+    }
+
+    fn search(manager: &QuicklinkManager, term: &str) -> Vec<String> {
+        manager
+            .list_quicklinks(Some(term.to_string()))
+            .unwrap()
+            .into_iter()
+            .map(|quicklink| quicklink.name)
+            .collect()
+    }
+
+    #[test]
+    fn search_takes_wildcards_literally() {
+        let manager = manager();
+        for (name, link) in [
+            ("Discounts", "https://shop.example/?off=50%25"),
+            ("Snake", "~/my_notes"),
+            ("Plain", "https://example.com/500"),
+        ] {
+            manager
+                .create_quicklink(
+                    name.to_string(),
+                    link.to_string(),
+                    None,
+                    None,
+                    None,
+                    Vec::new(),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(search(&manager, "50%"), vec!["Discounts"]);
+        assert_eq!(search(&manager, "my_"), vec!["Snake"]);
+        assert_eq!(search(&manager, "_"), vec!["Snake"]);
+        assert_eq!(search(&manager, "\\"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn search_matches_tags_not_their_encoding() {
+        let manager = manager();
+        manager
+            .create_quicklink(
+                "Docs".to_string(),
+                "https://docs.rs".to_string(),
+                None,
+                None,
+                None,
+                vec!["Rust".to_string(), "reference".to_string()],
+            )
+            .unwrap();
+
+        assert_eq!(search(&manager, "rust"), vec!["Docs"]);
+        assert_eq!(search(&manager, "ref"), vec!["Docs"]);
+        assert_eq!(search(&manager, "\""), Vec::<String>::new());
+        assert_eq!(search(&manager, ","), Vec::<String>::new());
+        assert_eq!(search(&manager, "rust\",\"ref"), Vec::<String>::new());
+    }
+}
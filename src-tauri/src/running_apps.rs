@@ -0,0 +1,429 @@
+use crate::{
+    app::App,
+    cache::AppCache,
+    desktop::DesktopFileManager,
+    error::AppError,
+    window_manager::{self, ActiveWindow, WindowQuery},
+};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{fs, os::unix::fs::MetadataExt, path::Path, sync::Mutex};
+
+// launchers and interpreters whose name says nothing about the app they run.
+// Matching by program name is only used to show and focus apps, never to
+// signal them.
+const GENERIC_PROGRAMS: &[&str] = &[
+    "sh",
+    "bash",
+    "dash",
+    "zsh",
+    "env",
+    "flatpak",
+    "snap",
+    "bwrap",
+    "pkexec",
+    "python",
+    "perl",
+    "node",
+    "electron",
+    "gjs",
+    "java",
+    "wine",
+    "gtk-launch",
+    "xdg-open",
+];
+
+/// Processes started by this launcher. Each one leads its own session, so
+/// its pid also identifies everything it forks.
+static LAUNCHED: Lazy<Mutex<Vec<LaunchRecord>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+struct LaunchRecord {
+    desktop_file_id: String,
+    pid: u32,
+    // tells the process apart from a later one that reuses its pid
+    start_time: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningApp {
+    pub desktop_file_id: String,
+    pub pids: Vec<u32>,
+}
+
+/// What the launcher knows about a process owned by the current user.
+struct ProcessInfo {
+    pid: u32,
+    executable: Option<String>,
+    argv0: Option<String>,
+    scope_unit: Option<String>,
+    session: Option<u32>,
+    start_time: Option<u64>,
+}
+
+fn file_name(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(String::from)
+}
+
+/// Unescapes `\xNN` sequences written by `systemd-escape`.
+fn unescape_unit_name(name: &str) -> String {
+    let mut bytes = Vec::with_capacity(name.len());
+    let raw = name.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\' && raw.get(i + 1) == Some(&b'x') {
+            if let Some(byte) = name
+                .get(i + 2..i + 4)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reads the session ID and start time from `/proc/<pid>/stat`.
+fn read_stat(proc_dir: &Path) -> Option<(u32, u64)> {
+    let stat = fs::read_to_string(proc_dir.join("stat")).ok()?;
+    // the command name in parentheses may contain spaces
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    Some((fields.get(3)?.parse().ok()?, fields.get(19)?.parse().ok()?))
+}
+
+fn read_process(proc_dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let cmdline = fs::read(proc_dir.join("cmdline")).ok()?;
+    // kernel threads have an empty command line
+    if cmdline.is_empty() {
+        return None;
+    }
+    let argv0 = cmdline
+        .split(|byte| *byte == 0)
+        .next()
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .and_then(|arg| file_name(&arg));
+
+    let executable = fs::read_link(proc_dir.join("exe"))
+        .ok()
+        .and_then(|path| path.to_str().and_then(file_name))
+        .map(|name| name.trim_end_matches(" (deleted)").to_string());
+
+    // the last component of the unified cgroup path, e.g. app-firefox-1a2b.scope
+    let scope_unit = fs::read_to_string(proc_dir.join("cgroup"))
+        .ok()
+        .and_then(|cgroup| {
+            cgroup
+                .lines()
+                .find_map(|line| line.strip_prefix("0::"))
+                .and_then(|path| path.rsplit('/').next())
+                .map(unescape_unit_name)
+        });
+
+    let stat = read_stat(proc_dir);
+
+    Some(ProcessInfo {
+        pid,
+        executable,
+        argv0,
+        scope_unit,
+        session: stat.map(|(session, _)| session),
+        start_time: stat.map(|(_, start_time)| start_time),
+    })
+}
+
+fn list_user_processes() -> Vec<ProcessInfo> {
+    let uid = unsafe { libc::getuid() };
+    let own_pid = std::process::id();
+
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            if pid == own_pid || entry.metadata().ok()?.uid() != uid {
+                return None;
+            }
+            read_process(&entry.path(), pid)
+        })
+        .collect()
+}
+
+fn app_id_stem(app: &App) -> Option<&str> {
+    app.desktop_file_id
+        .as_deref()
+        .map(|id| id.strip_suffix(".desktop").unwrap_or(id))
+}
+
+/// Returns the name of the program an app's `Exec` line runs, unless it is a
+/// generic launcher that many apps share.
+fn app_program(app: &App) -> Option<String> {
+    let args = DesktopFileManager::split_exec(app.exec.as_deref()?).ok()?;
+    // look past `env VAR=value` prefixes to the program itself
+    let program = args
+        .into_iter()
+        .filter_map(|(arg, _)| file_name(&arg))
+        .find(|name| name != "env" && !name.contains('='))?;
+
+    let is_generic = GENERIC_PROGRAMS.contains(&program.as_str())
+        // versioned interpreters such as python3.12
+        || program.starts_with("python");
+    (!is_generic).then_some(program)
+}
+
+/// Returns the app ID of a unit named like the ones this launcher, desktop
+/// shells and Flatpak create: `app-[<launcher>-]<id>-<suffix>.scope` or
+/// KDE's `app-[<launcher>-]<id>@<suffix>.service`. The ID can contain dashes
+/// itself, so the launcher part is returned as an alternative.
+fn scope_app_ids(scope_unit: &str) -> Vec<&str> {
+    let Some(rest) = scope_unit.strip_prefix("app-") else {
+        return Vec::new();
+    };
+    let split = if let Some(rest) = rest.strip_suffix(".scope") {
+        rest.rsplit_once('-')
+    } else if let Some(rest) = rest.strip_suffix(".service") {
+        rest.rsplit_once('@')
+    } else {
+        None
+    };
+    let Some((head, suffix)) = split else {
+        return Vec::new();
+    };
+    if head.is_empty() || suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Vec::new();
+    }
+
+    let mut ids = vec![head];
+    if let Some((launcher, id)) = head.split_once('-') {
+        if !launcher.is_empty() && !id.is_empty() {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+fn scope_belongs_to(scope_unit: &str, id: &str) -> bool {
+    scope_app_ids(scope_unit).contains(&id)
+}
+
+/// Records a process the launcher started for an app, so quitting the app
+/// can find it and everything in its session.
+pub fn record_launch(desktop_file_id: &str, pid: u32) {
+    let proc_dir = Path::new("/proc").join(pid.to_string());
+    let Some((_, start_time)) = read_stat(&proc_dir) else {
+        return;
+    };
+    let processes = list_user_processes();
+    let mut launched = LAUNCHED.lock().unwrap();
+    // forget sessions nothing runs in anymore
+    launched.retain(|record| {
+        processes
+            .iter()
+            .any(|process| process.session == Some(record.pid))
+    });
+    launched.push(LaunchRecord {
+        desktop_file_id: desktop_file_id.to_string(),
+        pid,
+        start_time,
+    });
+}
+
+/// Whether the process runs in a session the launcher started for the app.
+/// A session outlives its leader, but while the leader runs it has to be the
+/// process that was launched rather than one that reused its pid.
+fn in_launched_session(process: &ProcessInfo, processes: &[ProcessInfo], app: &App) -> bool {
+    let Some(session) = process.session else {
+        return false;
+    };
+    LAUNCHED.lock().unwrap().iter().any(|record| {
+        record.pid == session
+            && app.desktop_file_id.as_deref() == Some(record.desktop_file_id.as_str())
+            && processes
+                .iter()
+                .find(|leader| leader.pid == record.pid)
+                .is_none_or(|leader| leader.start_time == Some(record.start_time))
+    })
+}
+
+/// Whether the process is known to belong to the app: it runs in the app's
+/// systemd scope or in a session the launcher started for it.
+fn process_owned_by(process: &ProcessInfo, processes: &[ProcessInfo], app: &App) -> bool {
+    let in_scope = process
+        .scope_unit
+        .as_deref()
+        .zip(app_id_stem(app))
+        .is_some_and(|(scope, id)| scope_belongs_to(scope, id));
+    in_scope || in_launched_session(process, processes, app)
+}
+
+fn process_belongs_to(
+    process: &ProcessInfo,
+    processes: &[ProcessInfo],
+    app: &App,
+    program: Option<&str>,
+) -> bool {
+    process_owned_by(process, processes, app)
+        || program.is_some_and(|program| {
+            process.executable.as_deref() == Some(program)
+                || process.argv0.as_deref() == Some(program)
+        })
+}
+
+fn find_processes_in(processes: &[ProcessInfo], app: &App) -> Vec<u32> {
+    let program = app_program(app);
+    processes
+        .iter()
+        .filter(|process| process_belongs_to(process, processes, app, program.as_deref()))
+        .map(|process| process.pid)
+        .collect()
+}
+
+/// Returns the IDs of the current user's processes that look like they
+/// belong to `app`, including ones only matched by program name.
+pub fn find_processes(app: &App) -> Vec<u32> {
+    find_processes_in(&list_user_processes(), app)
+}
+
+/// Returns the IDs of the processes known to belong to `app`, the only ones
+/// it is safe to signal.
+fn find_owned_processes(app: &App) -> Vec<u32> {
+    let processes = list_user_processes();
+    processes
+        .iter()
+        .filter(|process| process_owned_by(process, &processes, app))
+        .map(|process| process.pid)
+        .collect()
+}
+
+/// The names an app's windows may carry as Wayland app ID or X11 `WM_CLASS`.
+fn app_wm_classes(app: &App) -> Vec<String> {
+    let mut wm_classes = Vec::new();
+    if let Some(class) = &app.startup_wm_class {
+        wm_classes.push(class.clone());
+    }
+    if let Some(id) = app_id_stem(app) {
+        wm_classes.push(id.to_string());
+    }
+    wm_classes
+}
+
+/// Raises a window of `app` if it is already running. Returns `false` when no
+/// instance is running or none of its windows could be focused.
+pub fn focus_running_instance(app: &App) -> bool {
+    let pids = find_processes(app);
+    if pids.is_empty() {
+        return false;
+    }
+    window_manager::focus_window(&WindowQuery {
+        pids,
+        wm_classes: app_wm_classes(app),
+    })
+}
+
+/// Finds the installed app a window belongs to, trying the most reliable
+/// signal first: the systemd scope of its process, then its app ID or
+/// `WM_CLASS`, then the name of its executable.
+pub fn app_for_window(apps: &[App], window: &ActiveWindow) -> Option<App> {
+    let process = window
+        .pid
+        .and_then(|pid| read_process(&Path::new("/proc").join(pid.to_string()), pid));
+
+    let by_scope = || {
+        let scope = process.as_ref()?.scope_unit.as_deref()?;
+        apps.iter()
+            .find(|app| app_id_stem(app).is_some_and(|id| scope_belongs_to(scope, id)))
+    };
+    let by_wm_class = || {
+        let class = window.wm_class.as_deref()?;
+        apps.iter().find(|app| {
+            app_wm_classes(app)
+                .iter()
+                .any(|wanted| wanted.eq_ignore_ascii_case(class))
+        })
+    };
+    let by_program = || {
+        let process = process.as_ref()?;
+        apps.iter().find(|app| {
+            let program = app_program(app);
+            program.is_some() && (process.executable == program || process.argv0 == program)
+        })
+    };
+
+    by_scope().or_else(by_wm_class).or_else(by_program).cloned()
+}
+
+fn find_app(desktop_file_id: &str) -> Result<App, AppError> {
+    AppCache::get_apps()?
+        .into_iter()
+        .find(|app| app.desktop_file_id.as_deref() == Some(desktop_file_id))
+        .ok_or_else(|| {
+            AppError::DesktopEntry(format!("No installed app with id {}", desktop_file_id))
+        })
+}
+
+fn signal_app(desktop_file_id: &str, signal: libc::c_int) -> Result<(), AppError> {
+    let app = find_app(desktop_file_id)?;
+    let pids = find_owned_processes(&app);
+    if pids.is_empty() {
+        let message = if find_processes(&app).is_empty() {
+            format!("{} is not running", app.name)
+        } else {
+            format!(
+                "{} wasn't started by the launcher or the desktop, so it can't be quit safely",
+                app.name
+            )
+        };
+        return Err(AppError::DesktopEntry(message));
+    }
+
+    for pid in pids {
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } == -1 {
+            let error = std::io::Error::last_os_error();
+            // the process may have exited on its own in the meantime
+            if error.raw_os_error() != Some(libc::ESRCH) {
+                return Err(error.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_running_apps() -> Result<Vec<RunningApp>, String> {
+    let apps = AppCache::get_apps().map_err(|e| e.to_string())?;
+    let processes = list_user_processes();
+
+    Ok(apps
+        .iter()
+        .filter_map(|app| {
+            let pids = find_processes_in(&processes, app);
+            if pids.is_empty() {
+                return None;
+            }
+            Some(RunningApp {
+                desktop_file_id: app.desktop_file_id.clone()?,
+                pids,
+            })
+        })
+        .collect())
+}
+
+/// Asks every process known to belong to the app to exit.
+#[tauri::command]
+pub fn quit_app(desktop_file_id: String) -> Result<(), String> {
+    signal_app(&desktop_file_id, libc::SIGTERM).map_err(|e| e.to_string())
+}
+
+/// Kills every process known to belong to the app immediately.
+#[tauri::command]
+pub fn force_quit_app(desktop_file_id: String) -> Result<(), String> {
+    signal_app(&desktop_file_id, libc::SIGKILL).map_err(|e| e.to_string())
+}
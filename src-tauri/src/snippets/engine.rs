@@ -9,7 +9,8 @@ use chrono::{DateTime, Duration, Local, Months};
 use enigo::Key as EnigoKey;
 use once_cell::sync::Lazy;
 use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
-use regex::Regex;
+use regex::{Captures, Regex};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -19,7 +20,7 @@ const BUFFER_SIZE: usize = 30;
 const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
 
 static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"\{(?P<name>\w+)(?P<attributes>(?:\s+\w+=(?:"[^"]*"|\S+))*)?(?P<modifiers>(?:\s*\|\s*[\w%-]+)*)\}"#).unwrap()
+    Regex::new(r#"\{(?P<name>\w+)(?P<attributes>(?:\s+\w+=(?:"[^"]*"|[^\s}]+))*)?(?P<modifiers>(?:\s*\|\s*[\w%-]+)*)\}"#).unwrap()
 });
 static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"\s*(?P<key>\w+)=(?:"(?P<q_value>[^"]*)"|(?P<uq_value>\S+))"#).unwrap()
@@ -32,6 +33,26 @@ pub struct ResolvedSnippet {
     pub cursor_pos: Option<usize>,
 }
 
+/// An input a template asks for with `{query}` or `{argument name="…"}`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Argument {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+/// Values the caller supplies for placeholders the engine can't look up
+/// itself.
+#[derive(Default)]
+pub struct PlaceholderValues {
+    /// Keyed by argument name. `{query}` and an unnamed `{argument}` both
+    /// read `query`.
+    pub arguments: HashMap<String, String>,
+    pub selection: Option<String>,
+    /// Applied to every substituted value, e.g. to URL-encode it.
+    pub escape: Option<fn(&str) -> String>,
+}
+
 #[derive(Debug)]
 struct ParsedPlaceholder<'a> {
     name: &'a str,
@@ -90,29 +111,30 @@ impl ExpansionEngine {
             }
         }
 
-        if let Ok(snippets) = self.snippet_manager.list_snippets(None) {
-            for snippet in snippets {
-                if buffer.ends_with(&snippet.keyword) {
-                    let (keyword, content, id) =
-                        (snippet.keyword.clone(), snippet.content.clone(), snippet.id);
-                    let manager = self.snippet_manager.clone();
-                    drop(buffer);
-                    self.expand_snippet(&keyword, &content);
-                    // run in a separate thread to not block input
-                    thread::spawn(move || {
-                        let _ = manager.snippet_was_used(id);
-                    });
-                    break;
-                }
-            }
+        let Some(matched) = self.snippet_manager.match_keyword(&buffer) else {
+            return;
+        };
+        drop(buffer);
+
+        // only a match reads the database
+        if let Ok(Some(snippet)) = self
+            .snippet_manager
+            .find_snippet_by_keyword(&matched.keyword)
+        {
+            let manager = self.snippet_manager.clone();
+            self.expand_snippet(&snippet.keyword, &snippet.content, matched.delimiter);
+            // run in a separate thread to not block input
+            thread::spawn(move || {
+                let _ = manager.snippet_was_used(snippet.id);
+            });
         }
     }
 
-    fn expand_snippet(&self, keyword: &str, content: &str) {
-        let mut backspaces = String::new();
-        for _ in 0..keyword.len() {
-            backspaces.push('\u{8}');
-        }
+    /// Replaces the typed keyword, and the delimiter that triggered it if
+    /// any, with the snippet. The delimiter is typed again afterwards.
+    fn expand_snippet(&self, keyword: &str, content: &str, delimiter: Option<char>) {
+        let typed_chars = keyword.chars().count() + usize::from(delimiter.is_some());
+        let backspaces = "\u{8}".repeat(typed_chars);
 
         let clipboard_manager_lock = CLIPBOARD_MANAGER_STATIC.lock().unwrap();
         let resolved_result = parse_and_resolve_placeholders(
@@ -132,7 +154,8 @@ impl ExpansionEngine {
             }
         };
 
-        let content_to_paste = resolved.content;
+        let mut content_to_paste = resolved.content;
+        content_to_paste.extend(delimiter);
 
         let chars_to_move_left = if let Some(pos) = resolved.cursor_pos {
             content_to_paste.chars().count() - pos
@@ -166,6 +189,53 @@ impl ExpansionEngine {
     }
 }
 
+fn parse_placeholder<'a>(cap: &Captures<'a>) -> ParsedPlaceholder<'a> {
+    ParsedPlaceholder {
+        name: cap.name("name").unwrap().as_str(),
+        attributes: parse_attributes(cap.name("attributes").map_or("", |m| m.as_str())),
+        modifiers: parse_modifiers(cap.name("modifiers").map_or("", |m| m.as_str())),
+    }
+}
+
+fn argument_of(placeholder: &ParsedPlaceholder) -> Option<Argument> {
+    let name = match placeholder.name {
+        "query" => "query",
+        "argument" => placeholder
+            .attributes
+            .get("name")
+            .copied()
+            .unwrap_or("query"),
+        _ => return None,
+    };
+    Some(Argument {
+        name: name.to_string(),
+        default: placeholder
+            .attributes
+            .get("default")
+            .map(|value| value.to_string()),
+    })
+}
+
+/// Lists the arguments a template asks for, once each, in order of first
+/// appearance.
+pub fn parse_arguments(raw_content: &str) -> Vec<Argument> {
+    let mut arguments: Vec<Argument> = Vec::new();
+    for cap in PLACEHOLDER_REGEX.captures_iter(raw_content) {
+        if let Some(argument) = argument_of(&parse_placeholder(&cap)) {
+            if !arguments.iter().any(|a| a.name == argument.name) {
+                arguments.push(argument);
+            }
+        }
+    }
+    arguments
+}
+
+pub fn contains_placeholder(raw_content: &str, name: &str) -> bool {
+    PLACEHOLDER_REGEX
+        .captures_iter(raw_content)
+        .any(|cap| &cap["name"] == name)
+}
+
 fn parse_attributes(attr_str: &str) -> HashMap<&str, &str> {
     ATTRIBUTE_REGEX
         .captures_iter(attr_str)
@@ -277,11 +347,23 @@ fn resolve_value<'a>(
     placeholder: &ParsedPlaceholder,
     snippet_manager: &SnippetManager,
     clipboard_manager: Option<&ClipboardHistoryManager>,
+    values: &PlaceholderValues,
 ) -> Result<String, AppError> {
     let now = Local::now();
 
+    if let Some(argument) = argument_of(placeholder) {
+        return Ok(values
+            .arguments
+            .get(&argument.name)
+            .filter(|value| !value.is_empty())
+            .cloned()
+            .or(argument.default)
+            .unwrap_or_default());
+    }
+
     match placeholder.name {
         "cursor" => Ok(String::new()),
+        "selection" => Ok(values.selection.clone().unwrap_or_default()),
         "uuid" => Ok(Uuid::new_v4().to_string().to_uppercase()),
         "clipboard" => {
             let offset: u32 = placeholder
@@ -393,6 +475,20 @@ pub fn parse_and_resolve_placeholders(
     raw_content: &str,
     snippet_manager: &SnippetManager,
     clipboard_manager: Option<&ClipboardHistoryManager>,
+) -> Result<ResolvedSnippet, AppError> {
+    resolve_placeholders(
+        raw_content,
+        snippet_manager,
+        clipboard_manager,
+        &PlaceholderValues::default(),
+    )
+}
+
+pub fn resolve_placeholders(
+    raw_content: &str,
+    snippet_manager: &SnippetManager,
+    clipboard_manager: Option<&ClipboardHistoryManager>,
+    values: &PlaceholderValues,
 ) -> Result<ResolvedSnippet, AppError> {
     let mut resolved_content = String::with_capacity(raw_content.len());
     let mut cursor_pos: Option<usize> = None;
@@ -402,24 +498,19 @@ pub fn parse_and_resolve_placeholders(
         let full_match = cap.get(0).unwrap();
         resolved_content.push_str(&raw_content[last_end..full_match.start()]);
 
-        let name = cap.name("name").unwrap().as_str();
-        let attributes = parse_attributes(cap.name("attributes").map_or("", |m| m.as_str()));
-        let modifiers = parse_modifiers(cap.name("modifiers").map_or("", |m| m.as_str()));
-
-        let placeholder = ParsedPlaceholder {
-            name,
-            attributes,
-            modifiers,
-        };
+        let placeholder = parse_placeholder(&cap);
 
         if placeholder.name == "cursor" {
             if cursor_pos.is_none() {
                 cursor_pos = Some(resolved_content.chars().count());
             }
         } else {
-            let value = resolve_value(&placeholder, snippet_manager, clipboard_manager)?;
+            let value = resolve_value(&placeholder, snippet_manager, clipboard_manager, values)?;
             let modified_value = apply_modifiers(value, &placeholder.modifiers);
-            resolved_content.push_str(&modified_value);
+            match values.escape {
+                Some(escape) => resolved_content.push_str(&escape(&modified_value)),
+                None => resolved_content.push_str(&modified_value),
+            }
         }
         last_end = full_match.end();
     }
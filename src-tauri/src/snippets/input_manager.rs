@@ -80,6 +80,14 @@ impl RdevInputManager {
             enigo: Mutex::new(Enigo::new(&enigo::Settings::default()).unwrap()),
         }
     }
+
+    fn send_ctrl_chord(&self, key: char) -> Result<()> {
+        let mut enigo = self.enigo.lock().unwrap();
+        enigo.key(EnigoKey::Control, enigo::Direction::Press)?;
+        enigo.key(EnigoKey::Unicode(key), enigo::Direction::Click)?;
+        enigo.key(EnigoKey::Control, enigo::Direction::Release)?;
+        Ok(())
+    }
 }
 
 impl InputManager for RdevInputManager {
@@ -116,13 +124,7 @@ impl InputManager for RdevInputManager {
             return self.inject_key_clicks(EnigoKey::Backspace, text.len());
         }
 
-        with_clipboard_text(text, || {
-            let mut enigo = self.enigo.lock().unwrap();
-            enigo.key(EnigoKey::Control, enigo::Direction::Press)?;
-            enigo.key(EnigoKey::Unicode('v'), enigo::Direction::Click)?;
-            enigo.key(EnigoKey::Control, enigo::Direction::Release)?;
-            Ok(())
-        })
+        with_clipboard_text(text, || self.send_ctrl_chord('v'))
     }
 
     fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()> {
@@ -293,6 +295,26 @@ impl EvdevInputManager {
         Ok(())
     }
 
+    fn send_ctrl_chord(&self, key: KeyCode) -> Result<()> {
+        let mut device = self.virtual_device.lock().unwrap();
+        let syn = evdev::InputEvent::new(
+            evdev::EventType::SYNCHRONIZATION.0,
+            evdev::SynchronizationCode::SYN_REPORT.0,
+            0,
+        );
+
+        device.emit(&[
+            evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 1),
+            syn.clone(),
+        ])?;
+        self.send_key_click(&mut device, key)?;
+        device.emit(&[
+            evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 0),
+            syn,
+        ])?;
+        Ok(())
+    }
+
     fn enigo_to_evdev(key: EnigoKey) -> Option<KeyCode> {
         match key {
             EnigoKey::LeftArrow => Some(KeyCode::KEY_LEFT),
@@ -398,25 +420,7 @@ impl InputManager for EvdevInputManager {
             return self.inject_key_clicks(EnigoKey::Backspace, text.len());
         }
 
-        with_clipboard_text(text, || {
-            let mut device = self.virtual_device.lock().unwrap();
-            let syn = evdev::InputEvent::new(
-                evdev::EventType::SYNCHRONIZATION.0,
-                evdev::SynchronizationCode::SYN_REPORT.0,
-                0,
-            );
-
-            device.emit(&[
-                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 1),
-                syn.clone(),
-            ])?;
-            self.send_key_click(&mut device, KeyCode::KEY_V)?;
-            device.emit(&[
-                evdev::InputEvent::new(evdev::EventType::KEY.0, KeyCode::KEY_LEFTCTRL.0, 0),
-                syn,
-            ])?;
-            Ok(())
-        })
+        with_clipboard_text(text, || self.send_ctrl_chord(KeyCode::KEY_V))
     }
 
     fn inject_key_clicks(&self, key: EnigoKey, count: usize) -> Result<()> {
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+/// Snippet keywords stored back to front, so the keyword typed text ends with
+/// is found by walking the text backwards. A lookup costs as much as the
+/// longest keyword, however many snippets there are.
+pub struct KeywordTrie<T> {
+    root: Node<T>,
+}
+
+struct Node<T> {
+    children: HashMap<char, Node<T>>,
+    value: Option<T>,
+}
+
+impl<T> Default for KeywordTrie<T> {
+    fn default() -> Self {
+        Self {
+            root: Node::default(),
+        }
+    }
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self {
+            children: HashMap::new(),
+            value: None,
+        }
+    }
+}
+
+impl<T> KeywordTrie<T> {
+    pub fn insert(&mut self, keyword: &str, value: T) {
+        if keyword.is_empty() {
+            return;
+        }
+        let mut node = &mut self.root;
+        for ch in keyword.chars().rev() {
+            node = node.children.entry(ch).or_default();
+        }
+        node.value = Some(value);
+    }
+
+    /// Returns the longest keyword `text` ends with that `accept` lets
+    /// through. `accept` gets the byte offset the keyword starts at in `text`
+    /// and the keyword's value.
+    pub fn longest_suffix<'a>(
+        &self,
+        text: &'a str,
+        accept: impl Fn(usize, &T) -> bool,
+    ) -> Option<&'a str> {
+        let mut node = &self.root;
+        let mut found = None;
+        for (index, ch) in text.char_indices().rev() {
+            let Some(next) = node.children.get(&ch) else {
+                break;
+            };
+            node = next;
+            if node
+                .value
+                .as_ref()
+                .is_some_and(|value| accept(index, value))
+            {
+                found = Some(&text[index..]);
+            }
+        }
+        found
+    }
+}
+
+impl<T> FromIterator<(String, T)> for KeywordTrie<T> {
+    fn from_iter<I: IntoIterator<Item = (String, T)>>(keywords: I) -> Self {
+        let mut trie = Self::default();
+        for (keyword, value) in keywords {
+            trie.insert(&keyword, value);
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    fn trie(keywords: &[&str]) -> KeywordTrie<()> {
+        keywords
+            .iter()
+            .map(|keyword| (keyword.to_string(), ()))
+            .collect()
+    }
+
+    #[test]
+    fn longest_keyword_wins() {
+        let trie = trie(&["sig", "!sig", "ig"]);
+        assert_eq!(trie.longest_suffix("hello !sig", |_, _| true), Some("!sig"));
+        assert_eq!(trie.longest_suffix("hello sig", |_, _| true), Some("sig"));
+    }
+
+    #[test]
+    fn no_match() {
+        let trie = trie(&["sig", "addr"]);
+        assert_eq!(trie.longest_suffix("signature", |_, _| true), None);
+        assert_eq!(trie.longest_suffix("", |_, _| true), None);
+        assert_eq!(
+            KeywordTrie::<()>::default().longest_suffix("sig", |_, _| true),
+            None
+        );
+    }
+
+    #[test]
+    fn empty_keywords_are_ignored() {
+        let trie = trie(&[""]);
+        assert_eq!(trie.longest_suffix("anything", |_, _| true), None);
+    }
+
+    #[test]
+    fn multibyte_keywords() {
+        let trie = trie(&["ß", "→→", "日本"]);
+        assert_eq!(trie.longest_suffix("straße", |_, _| true), None);
+        assert_eq!(trie.longest_suffix("groß", |_, _| true), Some("ß"));
+        assert_eq!(trie.longest_suffix("a→→", |_, _| true), Some("→→"));
+        assert_eq!(
+            trie.longest_suffix("こんにちは日本", |_, _| true),
+            Some("日本")
+        );
+    }
+
+    #[test]
+    fn rejected_keywords_fall_back_to_shorter_ones() {
+        let trie: KeywordTrie<bool> = [("sig".to_string(), true), ("!sig".to_string(), false)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            trie.longest_suffix("a !sig", |_, accepted| *accepted),
+            Some("sig")
+        );
+    }
+
+    #[test]
+    fn accept_gets_the_byte_offset_of_the_keyword() {
+        let trie = trie(&["日本"]);
+        let offset = Cell::new(None);
+        trie.longest_suffix("é日本", |start, _| {
+            offset.set(Some(start));
+            true
+        });
+        assert_eq!(offset.get(), Some("é".len()));
+    }
+
+    /// Lookups walk back from the end of the typed text, so they cost the
+    /// same however many keywords there are.
+    #[test]
+    fn lookup_cost_does_not_grow_with_keyword_count() {
+        fn lookup_time(size: usize) -> Duration {
+            const LOOKUPS: usize = 10_000;
+            let trie = (0..size)
+                .map(|i| (format!(";kw{}", i), ()))
+                .collect::<KeywordTrie<()>>();
+            let texts = ["typing along ;kw7", "typing along with no keyword"];
+            // the fastest of a few rounds, so other tests running alongside
+            // don't skew it
+            (0..5)
+                .map(|_| {
+                    let started = Instant::now();
+                    for _ in 0..LOOKUPS {
+                        for text in texts {
+                            black_box(trie.longest_suffix(black_box(text), |_, _| true));
+                        }
+                    }
+                    started.elapsed()
+                })
+                .min()
+                .unwrap()
+        }
+
+        let few = lookup_time(10);
+        let many = lookup_time(100_000);
+        assert!(
+            many < few * 4,
+            "{:?} with 100000 keywords against {:?} with 10",
+            many,
+            few
+        );
+    }
+}
@@ -1,13 +1,90 @@
 use crate::error::AppError;
-use crate::snippets::types::Snippet;
+use crate::migrations::{self, Migration};
+use crate::snippets::keywords::KeywordTrie;
+use crate::snippets::types::{Snippet, SnippetSettings, TriggerMode};
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, Result as RusqliteResult};
-use std::sync::{Arc, Mutex};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::{AppHandle, Manager};
 
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration::sql(
+        "create snippets table",
+        "CREATE TABLE IF NOT EXISTS snippets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            keyword TEXT NOT NULL UNIQUE,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+    ),
+    Migration::code("track snippet usage", |db| {
+        migrations::add_column_if_missing(
+            db,
+            "snippets",
+            "times_used",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        migrations::add_column_if_missing(
+            db,
+            "snippets",
+            "last_used_at",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        Ok(())
+    }),
+    Migration::sql(
+        "add per-snippet trigger mode",
+        "ALTER TABLE snippets ADD COLUMN trigger_mode TEXT",
+    ),
+];
+
+const SNIPPET_COLUMNS: &str =
+    "id, name, keyword, content, created_at, updated_at, times_used, last_used_at, trigger_mode";
+
+fn row_to_snippet(row: &rusqlite::Row) -> rusqlite::Result<Snippet> {
+    let created_at_ts: i64 = row.get(4)?;
+    let updated_at_ts: i64 = row.get(5)?;
+    let last_used_at_ts: i64 = row.get(7)?;
+    let trigger: Option<String> = row.get(8)?;
+    Ok(Snippet {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        keyword: row.get(2)?,
+        content: row.get(3)?,
+        created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
+        updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
+        times_used: row.get(6)?,
+        last_used_at: DateTime::from_timestamp(last_used_at_ts, 0).unwrap_or_default(),
+        trigger: trigger.as_deref().and_then(TriggerMode::parse),
+    })
+}
+
+/// Characters that end a word and can trigger an expansion. Enter and Tab
+/// aren't among them since what they did can't be taken back.
+fn is_delimiter(ch: char) -> bool {
+    !matches!(ch, '\n' | '\r' | '\t') && (ch.is_whitespace() || ch.is_ascii_punctuation())
+}
+
+/// A keyword typed text triggers.
+pub struct KeywordMatch {
+    pub keyword: String,
+    /// The delimiter typed after the keyword, which has to be put back after
+    /// the expansion.
+    pub delimiter: Option<char>,
+}
+
 #[derive(Clone)]
 pub struct SnippetManager {
     db: Arc<Mutex<Connection>>,
+    // kept in memory since the expansion engine checks it on every key press,
+    // along with each snippet's own trigger mode
+    keywords: Arc<RwLock<KeywordTrie<Option<TriggerMode>>>>,
+    settings_path: PathBuf,
+    settings: Arc<Mutex<SnippetSettings>>,
 }
 
 impl SnippetManager {
@@ -16,67 +93,131 @@ impl SnippetManager {
             .path()
             .app_local_data_dir()
             .map_err(|_| AppError::DirectoryNotFound)?;
-        let db_path = data_dir.join("snippets.sqlite");
-        let db = Connection::open(db_path)?;
+        let db = Connection::open(data_dir.join("snippets.sqlite"))?;
+        Self::with_connection(db, data_dir.join("snippet_settings.json"))
+    }
+
+    fn with_connection(db: Connection, settings_path: PathBuf) -> Result<Self, AppError> {
+        let settings = Self::read_settings(&settings_path)?;
         Ok(Self {
             db: Arc::new(Mutex::new(db)),
+            keywords: Arc::new(RwLock::new(KeywordTrie::default())),
+            settings_path,
+            settings: Arc::new(Mutex::new(settings)),
         })
     }
 
-    pub fn init_db(&self) -> RusqliteResult<()> {
-        let db = self.db.lock().unwrap();
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS snippets (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                keyword TEXT NOT NULL UNIQUE,
-                content TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
+    pub fn init_db(&self) -> Result<(), AppError> {
+        let mut db = self.db.lock().unwrap();
+        migrations::run(&mut db, MIGRATIONS)?;
+        self.load_keywords(&db)
+    }
+
+    fn load_keywords(&self, db: &Connection) -> Result<(), AppError> {
+        let mut stmt = db.prepare("SELECT keyword, trigger_mode FROM snippets")?;
+        let keywords = stmt
+            .query_map([], |row| {
+                let trigger: Option<String> = row.get(1)?;
+                Ok((row.get(0)?, trigger.as_deref().and_then(TriggerMode::parse)))
+            })?
+            .collect::<Result<KeywordTrie<_>, _>>()?;
+        *self.keywords.write().unwrap() = keywords;
+        Ok(())
+    }
 
-        let mut stmt = db.prepare("PRAGMA table_info(snippets)")?;
-        let columns: Vec<String> = stmt
-            .query_map([], |row| row.get(1))?
-            .collect::<Result<Vec<_>, _>>()?;
+    /// Rebuilds the keyword index after the database was changed behind the
+    /// manager's back, e.g. by restoring a backup.
+    pub fn reload_keywords(&self) -> Result<(), AppError> {
+        let db = self.db.lock().unwrap();
+        self.load_keywords(&db)
+    }
 
-        if !columns.contains(&"times_used".to_string()) {
-            db.execute(
-                "ALTER TABLE snippets ADD COLUMN times_used INTEGER NOT NULL DEFAULT 0",
-                [],
-            )?;
+    fn read_settings(path: &Path) -> Result<SnippetSettings, AppError> {
+        if !path.exists() {
+            return Ok(SnippetSettings::default());
         }
-        if !columns.contains(&"last_used_at".to_string()) {
-            db.execute(
-                "ALTER TABLE snippets ADD COLUMN last_used_at INTEGER NOT NULL DEFAULT 0",
-                [],
-            )?;
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(SnippetSettings::default());
         }
+        serde_json::from_str(&content).map_err(|e| AppError::Serialization(e.to_string()))
+    }
 
+    pub fn get_settings(&self) -> SnippetSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: SnippetSettings) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| AppError::Serialization(e.to_string()))?;
+        fs::write(&self.settings_path, content)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    /// Picks up settings written to disk by someone else, e.g. a restore.
+    pub fn reload_settings(&self) -> Result<(), AppError> {
+        let settings = Self::read_settings(&self.settings_path)?;
+        *self.settings.lock().unwrap() = settings;
         Ok(())
     }
 
+    /// Returns the keyword whose trigger `text` completes, preferring the
+    /// longest. Keywords that expand immediately are checked before those
+    /// waiting for a delimiter.
+    pub fn match_keyword(&self, text: &str) -> Option<KeywordMatch> {
+        let default_trigger = self.settings.lock().unwrap().trigger;
+        let keywords = self.keywords.read().unwrap();
+
+        let immediate = keywords.longest_suffix(text, |_, trigger| {
+            trigger.unwrap_or(default_trigger) == TriggerMode::Immediate
+        });
+        if let Some(keyword) = immediate {
+            return Some(KeywordMatch {
+                keyword: keyword.to_string(),
+                delimiter: None,
+            });
+        }
+
+        let delimiter = text.chars().last().filter(|ch| is_delimiter(*ch))?;
+        let typed = &text[..text.len() - delimiter.len_utf8()];
+        keywords
+            .longest_suffix(typed, |start, trigger| {
+                match trigger.unwrap_or(default_trigger) {
+                    TriggerMode::Immediate => false,
+                    TriggerMode::Delimiter => true,
+                    TriggerMode::WordBoundary => {
+                        typed[..start].chars().last().is_none_or(is_delimiter)
+                    }
+                }
+            })
+            .map(|keyword| KeywordMatch {
+                keyword: keyword.to_string(),
+                delimiter: Some(delimiter),
+            })
+    }
+
     pub fn create_snippet(
         &self,
         name: String,
         keyword: String,
         content: String,
+        trigger: Option<TriggerMode>,
     ) -> Result<i64, AppError> {
         let db = self.db.lock().unwrap();
         let now = Utc::now().timestamp();
         db.execute(
-            "INSERT INTO snippets (name, keyword, content, created_at, updated_at, times_used, last_used_at)
-             VALUES (?1, ?2, ?3, ?4, ?4, 0, 0)",
-            params![name, keyword, content, now],
+            "INSERT INTO snippets (name, keyword, content, created_at, updated_at, times_used, last_used_at, trigger_mode)
+             VALUES (?1, ?2, ?3, ?4, ?4, 0, 0, ?5)",
+            params![name, keyword, content, now, trigger.map(TriggerMode::as_str)],
         )?;
+        self.keywords.write().unwrap().insert(&keyword, trigger);
         Ok(db.last_insert_rowid())
     }
 
     pub fn list_snippets(&self, search_term: Option<String>) -> Result<Vec<Snippet>, AppError> {
         let db = self.db.lock().unwrap();
-        let mut query = "SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets".to_string();
+        let mut query = format!("SELECT {} FROM snippets", SNIPPET_COLUMNS);
         let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![];
 
         if let Some(term) = search_term {
@@ -91,21 +232,7 @@ impl SnippetManager {
         let params_ref: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
 
         let mut stmt = db.prepare(&query)?;
-        let snippets_iter = stmt.query_map(&params_ref[..], |row| {
-            let created_at_ts: i64 = row.get(4)?;
-            let updated_at_ts: i64 = row.get(5)?;
-            let last_used_at_ts: i64 = row.get(7)?;
-            Ok(Snippet {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                keyword: row.get(2)?,
-                content: row.get(3)?,
-                created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
-                updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
-                times_used: row.get(6)?,
-                last_used_at: DateTime::from_timestamp(last_used_at_ts, 0).unwrap_or_default(),
-            })
-        })?;
+        let snippets_iter = stmt.query_map(&params_ref[..], row_to_snippet)?;
 
         snippets_iter
             .collect::<Result<Vec<_>, _>>()
@@ -118,20 +245,22 @@ impl SnippetManager {
         name: String,
         keyword: String,
         content: String,
+        trigger: Option<TriggerMode>,
     ) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
         let now = Utc::now().timestamp();
         db.execute(
-            "UPDATE snippets SET name = ?1, keyword = ?2, content = ?3, updated_at = ?4 WHERE id = ?5",
-            params![name, keyword, content, now, id],
+            "UPDATE snippets SET name = ?1, keyword = ?2, content = ?3, updated_at = ?4, trigger_mode = ?5
+             WHERE id = ?6",
+            params![name, keyword, content, now, trigger.map(TriggerMode::as_str), id],
         )?;
-        Ok(())
+        self.load_keywords(&db)
     }
 
     pub fn delete_snippet(&self, id: i64) -> Result<(), AppError> {
         let db = self.db.lock().unwrap();
         db.execute("DELETE FROM snippets WHERE id = ?1", params![id])?;
-        Ok(())
+        self.load_keywords(&db)
     }
 
     pub fn snippet_was_used(&self, id: i64) -> Result<(), AppError> {
@@ -146,53 +275,129 @@ impl SnippetManager {
 
     pub fn find_snippet_by_keyword(&self, keyword: &str) -> Result<Option<Snippet>, AppError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare("SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets WHERE keyword = ?1")?;
-        let mut rows = stmt.query_map(params![keyword], |row| {
-            let created_at_ts: i64 = row.get(4)?;
-            let updated_at_ts: i64 = row.get(5)?;
-            let last_used_at_ts: i64 = row.get(7)?;
-            Ok(Snippet {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                keyword: row.get(2)?,
-                content: row.get(3)?,
-                created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
-                updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
-                times_used: row.get(6)?,
-                last_used_at: DateTime::from_timestamp(last_used_at_ts, 0).unwrap_or_default(),
-            })
-        })?;
-
-        if let Some(row) = rows.next() {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
-        }
+        db.query_row(
+            &format!(
+                "SELECT {} FROM snippets WHERE keyword = ?1",
+                SNIPPET_COLUMNS
+            ),
+            params![keyword],
+            row_to_snippet,
+        )
+        .optional()
+        .map_err(|e| e.into())
     }
 
     pub fn find_snippet_by_name(&self, name: &str) -> Result<Option<Snippet>, AppError> {
         let db = self.db.lock().unwrap();
-        let mut stmt = db.prepare("SELECT id, name, keyword, content, created_at, updated_at, times_used, last_used_at FROM snippets WHERE name = ?1 ORDER BY updated_at DESC LIMIT 1")?;
-        let mut rows = stmt.query_map(params![name], |row| {
-            let created_at_ts: i64 = row.get(4)?;
-            let updated_at_ts: i64 = row.get(5)?;
-            let last_used_at_ts: i64 = row.get(7)?;
-            Ok(Snippet {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                keyword: row.get(2)?,
-                content: row.get(3)?,
-                created_at: DateTime::from_timestamp(created_at_ts, 0).unwrap_or_default(),
-                updated_at: DateTime::from_timestamp(updated_at_ts, 0).unwrap_or_default(),
-                times_used: row.get(6)?,
-                last_used_at: DateTime::from_timestamp(last_used_at_ts, 0).unwrap_or_default(),
-            })
-        })?;
+        db.query_row(
+            &format!(
+                "SELECT {} FROM snippets WHERE name = ?1 ORDER BY updated_at DESC LIMIT 1",
+                SNIPPET_COLUMNS
+            ),
+            params![name],
+            row_to_snippet,
+        )
+        .optional()
+        .map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(default_trigger: TriggerMode) -> SnippetManager {
+        let manager = SnippetManager::with_connection(
+            Connection::open_in_memory().unwrap(),
+            PathBuf::from("/nonexistent/snippet_settings.json"),
+        )
+        .unwrap();
+        manager.init_db().unwrap();
+        *manager.settings.lock().unwrap() = SnippetSettings {
+            trigger: default_trigger,
+        };
+        manager
+    }
+
+    fn add(manager: &SnippetManager, keyword: &str, trigger: Option<TriggerMode>) {
+        manager
+            .create_snippet(
+                keyword.to_string(),
+                keyword.to_string(),
+                String::new(),
+                trigger,
+            )
+            .unwrap();
+    }
+
+    fn matched(manager: &SnippetManager, text: &str) -> Option<(String, Option<char>)> {
+        manager
+            .match_keyword(text)
+            .map(|m| (m.keyword, m.delimiter))
+    }
+
+    #[test]
+    fn immediate_keywords_match_as_typed() {
+        let manager = manager(TriggerMode::Immediate);
+        add(&manager, ";sig", None);
+        assert_eq!(matched(&manager, "hi ;sig"), Some((";sig".into(), None)));
+        assert_eq!(matched(&manager, "hi ;si"), None);
+    }
 
-        if let Some(row) = rows.next() {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+    #[test]
+    fn delimiter_keywords_wait_for_a_delimiter() {
+        let manager = manager(TriggerMode::Delimiter);
+        add(&manager, "addr", None);
+        assert_eq!(matched(&manager, "addr"), None);
+        assert_eq!(
+            matched(&manager, "myaddr "),
+            Some(("addr".into(), Some(' ')))
+        );
+    }
+
+    #[test]
+    fn enter_and_tab_are_not_delimiters() {
+        let manager = manager(TriggerMode::Delimiter);
+        add(&manager, "addr", None);
+        for text in ["addr\n", "addr\r", "addr\t"] {
+            assert_eq!(matched(&manager, text), None);
         }
     }
+
+    #[test]
+    fn word_boundary_keywords_need_a_word_of_their_own() {
+        let manager = manager(TriggerMode::Immediate);
+        add(&manager, "btw", Some(TriggerMode::WordBoundary));
+        assert_eq!(matched(&manager, "btw."), Some(("btw".into(), Some('.'))));
+        assert_eq!(
+            matched(&manager, "ok btw,"),
+            Some(("btw".into(), Some(',')))
+        );
+        assert_eq!(matched(&manager, "abtw "), None);
+        assert_eq!(matched(&manager, "btw"), None);
+    }
+
+    #[test]
+    fn immediate_keywords_are_checked_first() {
+        let manager = manager(TriggerMode::Immediate);
+        add(&manager, "ab", Some(TriggerMode::Delimiter));
+        add(&manager, "b ", None);
+        assert_eq!(matched(&manager, "ab "), Some(("b ".into(), None)));
+    }
+
+    #[test]
+    fn trigger_modes_are_stored() {
+        let manager = manager(TriggerMode::Immediate);
+        add(&manager, "btw", Some(TriggerMode::WordBoundary));
+        add(&manager, "addr", None);
+        let snippet = |keyword| {
+            manager
+                .find_snippet_by_keyword(keyword)
+                .unwrap()
+                .unwrap()
+                .trigger
+        };
+        assert_eq!(snippet("btw"), Some(TriggerMode::WordBoundary));
+        assert_eq!(snippet("addr"), None);
+    }
 }
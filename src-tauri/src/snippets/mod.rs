@@ -1,5 +1,6 @@
 pub mod engine;
 pub mod input_manager;
+pub mod keywords;
 pub mod manager;
 pub mod types;
 
@@ -8,7 +9,7 @@ use crate::error::AppError;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
-use types::Snippet;
+use types::{Snippet, SnippetSettings, TriggerMode};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,9 +32,10 @@ pub fn create_snippet(
     name: String,
     keyword: String,
     content: String,
+    trigger: Option<TriggerMode>,
 ) -> Result<i64, String> {
     app.state::<manager::SnippetManager>()
-        .create_snippet(name, keyword, content)
+        .create_snippet(name, keyword, content, trigger)
         .map_err(|e| e.to_string())
 }
 
@@ -51,9 +53,10 @@ pub fn update_snippet(
     name: String,
     keyword: String,
     content: String,
+    trigger: Option<TriggerMode>,
 ) -> Result<(), String> {
     app.state::<manager::SnippetManager>()
-        .update_snippet(id, name, keyword, content)
+        .update_snippet(id, name, keyword, content, trigger)
         .map_err(|e| e.to_string())
 }
 
@@ -64,6 +67,18 @@ pub fn delete_snippet(app: AppHandle, id: i64) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn get_snippet_settings(app: AppHandle) -> SnippetSettings {
+    app.state::<manager::SnippetManager>().get_settings()
+}
+
+#[tauri::command]
+pub fn set_snippet_settings(app: AppHandle, settings: SnippetSettings) -> Result<(), String> {
+    app.state::<manager::SnippetManager>()
+        .set_settings(settings)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn snippet_was_used(app: AppHandle, id: i64) -> Result<(), String> {
     app.state::<manager::SnippetManager>()
@@ -124,7 +139,7 @@ pub fn import_snippets(app: AppHandle, json_content: String) -> Result<ImportRes
     for snippet in snippets {
         let keyword = snippet.keyword;
 
-        match manager.create_snippet(snippet.name, keyword, snippet.text) {
+        match manager.create_snippet(snippet.name, keyword, snippet.text, None) {
             Ok(_) => snippets_added += 1,
             Err(AppError::Rusqlite(rusqlite::Error::SqliteFailure(e, Some(msg))))
                 if e.code == rusqlite::ErrorCode::ConstraintViolation
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -12,4 +12,46 @@ pub struct Snippet {
     pub updated_at: DateTime<Utc>,
     pub times_used: i32,
     pub last_used_at: DateTime<Utc>,
+    /// `None` follows [`SnippetSettings::trigger`].
+    pub trigger: Option<TriggerMode>,
+}
+
+/// What typing a keyword has to be followed by to expand it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TriggerMode {
+    /// Expands as soon as the keyword's last character is typed.
+    #[default]
+    Immediate,
+    /// Expands when a delimiter is typed after a keyword that starts a word,
+    /// so `;ad` stays as is inside `x;ad` and `;address`.
+    WordBoundary,
+    /// Expands when a delimiter is typed after the keyword.
+    Delimiter,
+}
+
+impl TriggerMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TriggerMode::Immediate => "immediate",
+            TriggerMode::WordBoundary => "wordBoundary",
+            TriggerMode::Delimiter => "delimiter",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "immediate" => Some(TriggerMode::Immediate),
+            "wordBoundary" => Some(TriggerMode::WordBoundary),
+            "delimiter" => Some(TriggerMode::Delimiter),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnippetSettings {
+    /// Used by snippets that don't pick a trigger mode of their own.
+    pub trigger: TriggerMode,
 }
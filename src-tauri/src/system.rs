@@ -8,44 +8,255 @@ pub struct Application {
     bundle_id: Option<String>,
 }
 
+#[cfg(target_os = "linux")]
+impl From<&crate::app::App> for Application {
+    // desktop-file IDs play the role of bundle identifiers on Linux
+    fn from(app: &crate::app::App) -> Self {
+        Application {
+            name: app.name.clone(),
+            path: app.desktop_file_path.clone().unwrap_or_default(),
+            bundle_id: app.desktop_file_id.clone(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedItem {
+    id: String,
+    name: String,
+    original_parent: String,
+    original_path: String,
+    time_deleted: i64,
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+impl From<&trash::TrashItem> for TrashedItem {
+    fn from(item: &trash::TrashItem) -> Self {
+        TrashedItem {
+            id: item.id.to_string_lossy().into_owned(),
+            name: item.name.to_string_lossy().into_owned(),
+            original_parent: item.original_parent.to_string_lossy().into_owned(),
+            original_path: item.original_path().to_string_lossy().into_owned(),
+            time_deleted: item.time_deleted,
+        }
+    }
+}
+
 #[tauri::command]
 pub fn trash(paths: Vec<String>) -> Result<(), String> {
     trash::delete_all(paths).map_err(|e| e.to_string())
 }
 
+/// Looks up trashed items by the IDs handed out by `list_trash`.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn find_trash_items(ids: &[String]) -> Result<Vec<trash::TrashItem>, String> {
+    let items: Vec<trash::TrashItem> = trash::os_limited::list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|item| ids.contains(&item.id.to_string_lossy().into_owned()))
+        .collect();
+    if items.len() < ids.len() {
+        return Err("Some items are no longer in the trash".to_string());
+    }
+    Ok(items)
+}
+
+/// Lists trashed items, most recently deleted first.
+#[tauri::command]
+pub fn list_trash() -> Result<Vec<TrashedItem>, String> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    {
+        let mut items = trash::os_limited::list().map_err(|e| e.to_string())?;
+        items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+        Ok(items.iter().map(TrashedItem::from).collect())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    Err("Listing the trash is not supported on this platform".to_string())
+}
+
+/// Moves trashed items back to where they were deleted from. Fails without
+/// restoring anything if a file already exists at one of those locations.
+#[tauri::command]
+pub fn restore_trash_items(ids: Vec<String>) -> Result<(), String> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    {
+        let items = find_trash_items(&ids)?;
+        trash::os_limited::restore_all(items).map_err(|e| match e {
+            trash::Error::RestoreCollision { path, .. } => {
+                format!("{} already exists", path.display())
+            }
+            trash::Error::RestoreTwins { path, .. } => format!(
+                "More than one of the items was deleted from {}",
+                path.display()
+            ),
+            e => e.to_string(),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let _ = ids;
+        Err("Restoring from the trash is not supported on this platform".to_string())
+    }
+}
+
+/// Permanently deletes trashed items.
+#[tauri::command]
+pub fn purge_trash_items(ids: Vec<String>) -> Result<(), String> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    {
+        let items = find_trash_items(&ids)?;
+        trash::os_limited::purge_all(items).map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let _ = ids;
+        Err("Deleting from the trash is not supported on this platform".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn empty_trash() -> Result<(), String> {
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    {
+        let items = trash::os_limited::list().map_err(|e| e.to_string())?;
+        trash::os_limited::purge_all(items).map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    Err("Emptying the trash is not supported on this platform".to_string())
+}
+
+/// Shows `paths` selected in the file manager, or with `folder` set, opens
+/// each of them as a folder.
 #[tauri::command]
-pub fn show_in_finder(path: String) -> Result<(), String> {
+pub fn show_in_finder(paths: Vec<String>, folder: Option<bool>) -> Result<(), String> {
+    let folder = folder.unwrap_or(false);
+
     #[cfg(target_os = "windows")]
     {
-        Command::new("explorer")
-            .args(["/select,", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+        // explorer can only select one item per window
+        for path in &paths {
+            let mut command = Command::new("explorer");
+            if folder {
+                command.arg(path);
+            } else {
+                command.args(["/select,", path]);
+            }
+            command.spawn().map_err(|e| e.to_string())?;
+        }
     }
 
     #[cfg(target_os = "macos")]
     {
-        Command::new("open")
-            .args(["-R", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+        let mut command = Command::new("open");
+        if !folder {
+            command.arg("-R");
+        }
+        command.args(&paths).spawn().map_err(|e| e.to_string())?;
     }
 
     #[cfg(target_os = "linux")]
     {
-        let path = std::path::Path::new(&path);
-        let parent = path.parent().unwrap_or(path).as_os_str();
-        Command::new("xdg-open")
-            .arg(parent)
-            .spawn()
-            .map_err(|e| e.to_string())?;
+        if file_manager_service_available() {
+            let method = if folder { "ShowFolders" } else { "ShowItems" };
+            call_file_manager(method, &paths)?;
+        } else if folder {
+            for path in &paths {
+                Command::new("xdg-open")
+                    .arg(path)
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+            }
+        } else {
+            let mut parents: Vec<&std::path::Path> = Vec::new();
+            for path in &paths {
+                let path = std::path::Path::new(path);
+                let parent = path.parent().unwrap_or(path);
+                if !parents.contains(&parent) {
+                    parents.push(parent);
+                }
+            }
+            for parent in parents {
+                Command::new("xdg-open")
+                    .arg(parent)
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+const FILE_MANAGER_SERVICE: &str = "org.freedesktop.FileManager1";
+
+/// Whether a file manager implementing `org.freedesktop.FileManager1` is
+/// running or can be started by D-Bus activation.
+#[cfg(target_os = "linux")]
+fn file_manager_service_available() -> bool {
+    let Ok(connection) = zbus::blocking::Connection::session() else {
+        return false;
+    };
+    let Ok(proxy) = zbus::blocking::fdo::DBusProxy::new(&connection) else {
+        return false;
+    };
+
+    let Ok(name) = zbus::names::BusName::try_from(FILE_MANAGER_SERVICE) else {
+        return false;
+    };
+    if proxy.name_has_owner(name).unwrap_or(false) {
+        return true;
     }
+    proxy
+        .list_activatable_names()
+        .map(|names| names.iter().any(|n| n.as_str() == FILE_MANAGER_SERVICE))
+        .unwrap_or(false)
+}
+
+/// Calls `ShowItems` or `ShowFolders` with all of `paths`, so they share
+/// one file manager window.
+#[cfg(target_os = "linux")]
+fn call_file_manager(method: &str, paths: &[String]) -> Result<(), String> {
+    let uris = paths
+        .iter()
+        .map(|path| {
+            let path = std::path::Path::new(path);
+            let absolute = if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                std::env::current_dir()
+                    .map_err(|e| e.to_string())?
+                    .join(path)
+            };
+            url::Url::from_file_path(&absolute)
+                .map(|uri| uri.to_string())
+                .map_err(|_| format!("Invalid path: {}", absolute.display()))
+        })
+        .collect::<Result<Vec<String>, String>>()?;
 
+    let connection = zbus::blocking::Connection::session().map_err(|e| e.to_string())?;
+    connection
+        .call_method(
+            Some(FILE_MANAGER_SERVICE),
+            "/org/freedesktop/FileManager1",
+            Some(FILE_MANAGER_SERVICE),
+            method,
+            &(uris, ""),
+        )
+        .map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_applications(_path: Option<String>) -> Result<Vec<Application>, String> {
+pub fn get_applications(path: Option<String>) -> Result<Vec<Application>, String> {
+    #[cfg(not(target_os = "linux"))]
+    let _ = path;
+
     #[cfg(target_os = "macos")]
     {
         let script = r#"
@@ -92,14 +303,19 @@ pub fn get_applications(_path: Option<String>) -> Result<Vec<Application>, Strin
 
     #[cfg(target_os = "linux")]
     {
-        Ok(crate::get_installed_apps()
-            .into_iter()
-            .map(|app| Application {
-                name: app.name,
-                path: app.exec.unwrap_or_default(),
-                bundle_id: None,
-            })
-            .collect())
+        let apps = crate::get_installed_apps();
+        match path {
+            // only the apps able to open the given file, the default one first
+            Some(path) => {
+                let mime_type = crate::mime::mime_type_of(std::path::Path::new(&path));
+                Ok(crate::mime::MimeAssociations::new(&apps)
+                    .all_apps(&mime_type)
+                    .into_iter()
+                    .map(Application::from)
+                    .collect())
+            }
+            None => Ok(apps.iter().map(Application::from).collect()),
+        }
     }
 
     #[cfg(target_os = "windows")]
@@ -136,10 +352,23 @@ pub fn get_applications(_path: Option<String>) -> Result<Vec<Application>, Strin
 
 #[tauri::command]
 pub fn get_default_application(path: String) -> Result<Application, String> {
-    Err(format!(
-        "get_default_application for '{}' is not yet implemented for this platform.",
-        path
-    ))
+    #[cfg(target_os = "linux")]
+    {
+        let apps = crate::get_installed_apps();
+        let mime_type = crate::mime::mime_type_of(std::path::Path::new(&path));
+        crate::mime::MimeAssociations::new(&apps)
+            .default_app(&mime_type)
+            .map(Application::from)
+            .ok_or_else(|| format!("No application found to open '{}' ({})", path, mime_type))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(format!(
+            "get_default_application for '{}' is not yet implemented for this platform.",
+            path
+        ))
+    }
 }
 
 #[tauri::command]
@@ -174,7 +403,42 @@ pub fn get_frontmost_application() -> Result<Application, String> {
         }
     }
 
-    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    #[cfg(target_os = "linux")]
+    {
+        let window = crate::window_manager::frontmost_window()
+            .ok_or_else(|| "Could not determine frontmost application".to_string())?;
+
+        if let Some(app) =
+            crate::running_apps::app_for_window(&crate::get_installed_apps(), &window)
+        {
+            return Ok(Application::from(&app));
+        }
+
+        // the window belongs to a program without a desktop entry
+        let executable = window
+            .pid
+            .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok());
+        let name = window
+            .wm_class
+            .clone()
+            .or_else(|| {
+                executable
+                    .as_ref()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .ok_or_else(|| "Could not determine frontmost application".to_string())?;
+
+        Ok(Application {
+            name,
+            path: executable
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            bundle_id: None,
+        })
+    }
+
+    #[cfg(target_os = "windows")]
     {
         Err("get_frontmost_application is not yet implemented for this platform.".to_string())
     }
@@ -0,0 +1,812 @@
+use crate::{desktop::DesktopFileManager, error::AppError};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{Read, Write},
+    os::unix::{fs::OpenOptionsExt, net::UnixStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use wayland_client::{
+    backend::ObjectId,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_registry, wl_seat},
+    Connection as WaylandConnection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, GetPropertyReply, Window,
+    },
+    rust_connection::RustConnection,
+    CURRENT_TIME,
+};
+
+const SWAY_RUN_COMMAND: u32 = 0;
+const SWAY_GET_TREE: u32 = 4;
+
+// the window that had focus when the launcher was last shown
+static PREVIOUS_WINDOW: Lazy<Mutex<Option<ActiveWindow>>> = Lazy::new(|| Mutex::new(None));
+// GNOME Shell only allows `Eval` in unsafe mode since GNOME 41, so it isn't
+// tried again once it failed
+static GNOME_SHELL_EVAL_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// The windows to look for when raising an app: any window owned by one of
+/// `pids`, or whose app ID / `WM_CLASS` matches one of `wm_classes`.
+pub struct WindowQuery {
+    pub pids: Vec<u32>,
+    pub wm_classes: Vec<String>,
+}
+
+impl WindowQuery {
+    fn matches(&self, pid: Option<u32>, wm_class: Option<&str>) -> bool {
+        pid.is_some_and(|pid| self.pids.contains(&pid))
+            || wm_class.is_some_and(|class| {
+                self.wm_classes
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(class))
+            })
+    }
+}
+
+/// The focused window, as far as the window manager tells us. Wayland
+/// compositors without an IPC of their own only report the app ID.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWindow {
+    pub pid: Option<u32>,
+    pub wm_class: Option<String>,
+}
+
+impl ActiveWindow {
+    /// Whether this is one of the launcher's own windows. Compositors that
+    /// only report the app ID are matched on the executable name, which is
+    /// what the launcher's windows use.
+    fn is_launcher(&self) -> bool {
+        if let Some(pid) = self.pid {
+            return pid == std::process::id();
+        }
+        let executable = env::current_exe().ok();
+        let name = executable
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str());
+        self.wm_class
+            .as_deref()
+            .zip(name)
+            .is_some_and(|(class, name)| class.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    Sway,
+    Hyprland,
+    GnomeShell,
+    KWin,
+    Wlroots,
+    X11,
+}
+
+/// Returns the window management interfaces worth trying in this session, most
+/// specific first.
+fn available_backends() -> Vec<Backend> {
+    let mut backends = Vec::new();
+    if env::var_os("SWAYSOCK").is_some() {
+        backends.push(Backend::Sway);
+    }
+    if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        backends.push(Backend::Hyprland);
+    }
+    for desktop in DesktopFileManager::current_desktops() {
+        match desktop.as_str() {
+            "GNOME" if !GNOME_SHELL_EVAL_FAILED.load(Ordering::Relaxed) => {
+                backends.push(Backend::GnomeShell)
+            }
+            "KDE" => backends.push(Backend::KWin),
+            _ => {}
+        }
+    }
+    // GNOME and KDE don't implement the wlroots protocols
+    let is_gnome_or_kde = backends
+        .iter()
+        .any(|b| matches!(b, Backend::GnomeShell | Backend::KWin));
+    if env::var_os("WAYLAND_DISPLAY").is_some() && !is_gnome_or_kde {
+        backends.push(Backend::Wlroots);
+    }
+    if env::var_os("DISPLAY").is_some() {
+        backends.push(Backend::X11);
+    }
+    backends
+}
+
+/// Raises and focuses the first window matching `query`. Returns `false` when
+/// no window management interface found one.
+pub fn focus_window(query: &WindowQuery) -> bool {
+    for backend in available_backends() {
+        let result = match backend {
+            Backend::Sway => sway_focus(query),
+            Backend::Hyprland => hyprland_focus(query),
+            Backend::GnomeShell => gnome_shell_focus(query),
+            Backend::KWin => kwin_focus(query),
+            Backend::Wlroots => wlroots_focus(query),
+            Backend::X11 => x11_focus(query),
+        };
+        match result {
+            Ok(true) => return true,
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to focus window via {:?}: {}", backend, e),
+        }
+    }
+    false
+}
+
+/// Returns the window that currently has focus, asking each available window
+/// management interface in turn.
+pub fn active_window() -> Option<ActiveWindow> {
+    for backend in available_backends() {
+        let result = match backend {
+            Backend::Sway => sway_active_window(),
+            Backend::Hyprland => hyprland_active_window(),
+            Backend::GnomeShell => gnome_shell_active_window(),
+            Backend::KWin => kwin_active_window(),
+            Backend::Wlroots => wlroots_active_window(),
+            Backend::X11 => x11_active_window(),
+        };
+        match result {
+            Ok(Some(window)) => return Some(window),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to get active window via {:?}: {}", backend, e),
+        }
+    }
+    None
+}
+
+/// Remembers the focused window so [`frontmost_window`] can still report it
+/// once the launcher has taken focus. Call before showing the launcher; the
+/// window manager is asked on another thread so showing it isn't delayed.
+pub fn remember_previous_window() {
+    thread::spawn(|| {
+        // held while asking, so `frontmost_window` waits for the answer
+        let mut previous = PREVIOUS_WINDOW.lock().unwrap();
+        // the launcher may already have focus, in which case nothing is known
+        *previous = active_window().filter(|window| !window.is_launcher());
+    });
+}
+
+/// The focused window, or while the launcher has focus, the window that had
+/// it before the launcher was shown.
+pub fn frontmost_window() -> Option<ActiveWindow> {
+    match active_window() {
+        Some(window) if !window.is_launcher() => Some(window),
+        _ => PREVIOUS_WINDOW.lock().unwrap().clone(),
+    }
+}
+
+fn parse_active_window_json(json: &str) -> Result<Option<ActiveWindow>, AppError> {
+    serde_json::from_str(json).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+fn ipc_error(e: impl std::fmt::Display) -> AppError {
+    AppError::WindowManager(e.to_string())
+}
+
+fn sway_request(message_type: u32, payload: &str) -> Result<serde_json::Value, AppError> {
+    let socket_path = env::var("SWAYSOCK").map_err(ipc_error)?;
+    let mut stream = UnixStream::connect(socket_path)?;
+
+    let mut message = b"i3-ipc".to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body).map_err(|e| AppError::Serialization(e.to_string()))
+}
+
+fn sway_run_command(command: &str) -> Result<bool, AppError> {
+    let reply = sway_request(SWAY_RUN_COMMAND, command)?;
+    Ok(reply.as_array().is_some_and(|results| {
+        results
+            .iter()
+            .any(|r| r.get("success").and_then(|s| s.as_bool()) == Some(true))
+    }))
+}
+
+fn sway_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    for pid in &query.pids {
+        if sway_run_command(&format!("[pid={}] focus", pid))? {
+            return Ok(true);
+        }
+    }
+    for class in &query.wm_classes {
+        let pattern = format!("(?i)^{}$", regex::escape(class));
+        for criterion in ["app_id", "class"] {
+            if sway_run_command(&format!("[{}=\"{}\"] focus", criterion, pattern))? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn find_focused_sway_node(node: &serde_json::Value) -> Option<&serde_json::Value> {
+    if node.get("focused").and_then(|f| f.as_bool()) == Some(true) {
+        return Some(node);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(*key).and_then(|n| n.as_array()))
+        .flatten()
+        .find_map(find_focused_sway_node)
+}
+
+fn sway_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let tree = sway_request(SWAY_GET_TREE, "")?;
+    // an empty workspace can be focused too, but it has no pid
+    Ok(find_focused_sway_node(&tree)
+        .filter(|node| node.get("pid").is_some())
+        .map(|node| ActiveWindow {
+            pid: node.get("pid").and_then(|p| p.as_u64()).map(|p| p as u32),
+            wm_class: node
+                .get("app_id")
+                .and_then(|a| a.as_str())
+                .or_else(|| {
+                    node.pointer("/window_properties/class")
+                        .and_then(|c| c.as_str())
+                })
+                .map(String::from),
+        }))
+}
+
+fn hyprland_socket_path() -> Option<PathBuf> {
+    let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    let mut candidates = Vec::new();
+    if let Ok(runtime_dir) = env::var("XDG_RUNTIME_DIR") {
+        candidates.push(PathBuf::from(runtime_dir).join("hypr"));
+    }
+    candidates.push(PathBuf::from("/tmp/hypr"));
+
+    candidates
+        .into_iter()
+        .map(|dir| dir.join(&signature).join(".socket.sock"))
+        .find(|path| path.exists())
+}
+
+fn hyprland_request(request: &str) -> Result<String, AppError> {
+    let socket_path = hyprland_socket_path()
+        .ok_or_else(|| AppError::WindowManager("Hyprland socket not found".to_string()))?;
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+fn hyprland_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&hyprland_request("j/clients")?)
+        .map_err(|e| AppError::Serialization(e.to_string()))?;
+
+    let client = clients.iter().find(|client| {
+        let pid = client.get("pid").and_then(|p| p.as_u64()).map(|p| p as u32);
+        query.matches(pid, client.get("class").and_then(|c| c.as_str()))
+            || query.matches(None, client.get("initialClass").and_then(|c| c.as_str()))
+    });
+    let Some(address) = client
+        .and_then(|c| c.get("address"))
+        .and_then(|a| a.as_str())
+    else {
+        return Ok(false);
+    };
+
+    let reply = hyprland_request(&format!("dispatch focuswindow address:{}", address))?;
+    Ok(reply.trim() == "ok")
+}
+
+fn hyprland_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let window: serde_json::Value = serde_json::from_str(&hyprland_request("j/activewindow")?)
+        .map_err(|e| AppError::Serialization(e.to_string()))?;
+    // Hyprland replies with an empty object when nothing is focused
+    if window.get("address").is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ActiveWindow {
+        pid: window.get("pid").and_then(|p| p.as_u64()).map(|p| p as u32),
+        wm_class: window
+            .get("class")
+            .and_then(|c| c.as_str())
+            .map(String::from),
+    }))
+}
+
+/// Evaluates JavaScript inside GNOME Shell. This only works when the shell
+/// allows `Eval`, e.g. in unsafe mode or with an extension that enables it.
+fn gnome_shell_eval(script: &str) -> Result<String, AppError> {
+    let connection = zbus::blocking::Connection::session().map_err(ipc_error)?;
+    let result = gnome_shell_eval_on(&connection, script);
+    if result.is_err() {
+        GNOME_SHELL_EVAL_FAILED.store(true, Ordering::Relaxed);
+    }
+    result
+}
+
+fn gnome_shell_eval_on(
+    connection: &zbus::blocking::Connection,
+    script: &str,
+) -> Result<String, AppError> {
+    let reply = connection
+        .call_method(
+            Some("org.gnome.Shell"),
+            "/org/gnome/Shell",
+            Some("org.gnome.Shell"),
+            "Eval",
+            &(script,),
+        )
+        .map_err(ipc_error)?;
+    let (success, result): (bool, String) = reply.body().deserialize().map_err(ipc_error)?;
+
+    if success {
+        Ok(result)
+    } else {
+        Err(AppError::WindowManager(format!(
+            "GNOME Shell refused to evaluate the script: {}",
+            result
+        )))
+    }
+}
+
+fn to_js(value: &impl serde::Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+fn gnome_shell_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    let script = format!(
+        r#"(() => {{
+            const pids = {pids};
+            const classes = {classes}.map(c => c.toLowerCase());
+            const win = global.get_window_actors()
+                .map(actor => actor.meta_window)
+                .find(w => pids.includes(w.get_pid())
+                    || classes.includes((w.get_wm_class() || '').toLowerCase()));
+            if (!win) return false;
+            Main.activateWindow(win);
+            return true;
+        }})()"#,
+        pids = to_js(&query.pids),
+        classes = to_js(&query.wm_classes),
+    );
+    Ok(gnome_shell_eval(&script)?.trim() == "true")
+}
+
+fn gnome_shell_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let script = r#"(() => {
+        const win = global.display.focus_window;
+        return win ? { pid: win.get_pid(), wmClass: win.get_wm_class() } : null;
+    })()"#;
+    parse_active_window_json(&gnome_shell_eval(script)?)
+}
+
+const KWIN_REPLY_PATH: &str = "/KWinScriptReply";
+
+/// Receives the value a KWin script passes back through `callDBus`.
+struct KWinScriptReply {
+    sender: mpsc::Sender<String>,
+}
+
+#[zbus::interface(name = "dev.raycast.Linux.KWinScriptReply")]
+impl KWinScriptReply {
+    fn reply(&self, value: String) {
+        let _ = self.sender.send(value);
+    }
+}
+
+/// Writes a script for KWin to load into the user's runtime directory,
+/// readable only by the user and never over an existing file.
+fn write_kwin_script(plugin_name: &str, script: &str) -> Result<PathBuf, AppError> {
+    let runtime_dir = env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::WindowManager("XDG_RUNTIME_DIR is not set".to_string()))?;
+    let path = runtime_dir.join(format!("{}.js", plugin_name));
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    if let Err(e) = file.write_all(script.as_bytes()) {
+        let _ = fs::remove_file(&path);
+        return Err(e.into());
+    }
+    Ok(path)
+}
+
+/// Loads `script` into KWin, runs it once and returns the string it hands to
+/// `reply(...)`. KWin scripts can't return values directly, so the script
+/// calls back into an object served on our own bus connection.
+fn kwin_eval(script: &str) -> Result<String, AppError> {
+    let connection = zbus::blocking::Connection::session().map_err(ipc_error)?;
+    let unique_name = connection
+        .unique_name()
+        .map(|name| name.to_string())
+        .ok_or_else(|| AppError::WindowManager("No unique D-Bus name".to_string()))?;
+
+    let (sender, receiver) = mpsc::channel();
+    connection
+        .object_server()
+        .at(KWIN_REPLY_PATH, KWinScriptReply { sender })
+        .map_err(ipc_error)?;
+
+    let script = format!(
+        r#"function reply(value) {{
+            callDBus({service}, {path}, "dev.raycast.Linux.KWinScriptReply", "Reply", String(value));
+        }}
+        {script}"#,
+        service = to_js(&unique_name),
+        path = to_js(&KWIN_REPLY_PATH),
+        script = script,
+    );
+
+    let plugin_name = format!(
+        "raycast-linux-{}-{:016x}",
+        std::process::id(),
+        rand::random::<u64>()
+    );
+    let script_path = write_kwin_script(&plugin_name, &script)?;
+
+    let result = (|| {
+        let reply = connection
+            .call_method(
+                Some("org.kde.KWin"),
+                "/Scripting",
+                Some("org.kde.kwin.Scripting"),
+                "loadScript",
+                &(script_path.to_string_lossy().as_ref(), plugin_name.as_str()),
+            )
+            .map_err(ipc_error)?;
+        let script_id: i32 = reply.body().deserialize().map_err(ipc_error)?;
+
+        connection
+            .call_method(
+                Some("org.kde.KWin"),
+                format!("/Scripting/Script{}", script_id).as_str(),
+                Some("org.kde.kwin.Script"),
+                "run",
+                &(),
+            )
+            .map_err(ipc_error)?;
+
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .map_err(|_| AppError::WindowManager("KWin script did not reply".to_string()))
+    })();
+
+    let _ = connection.call_method(
+        Some("org.kde.KWin"),
+        "/Scripting",
+        Some("org.kde.kwin.Scripting"),
+        "unloadScript",
+        &(plugin_name.as_str(),),
+    );
+    let _ = fs::remove_file(&script_path);
+    result
+}
+
+// KWin 6 renamed clients to windows
+const KWIN_WINDOW_HELPERS: &str = r#"
+    const windowList = () => workspace.windowList ? workspace.windowList() : workspace.clientList();
+    const activeWindow = () => workspace.windowList ? workspace.activeWindow : workspace.activeClient;
+"#;
+
+fn kwin_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    let script = format!(
+        r#"{helpers}
+        const pids = {pids};
+        const classes = {classes}.map(c => c.toLowerCase());
+        const win = windowList().find(w => pids.includes(w.pid)
+            || classes.includes(String(w.resourceClass).toLowerCase()));
+        if (win) {{
+            if (workspace.windowList) {{
+                workspace.activeWindow = win;
+            }} else {{
+                workspace.activeClient = win;
+            }}
+        }}
+        reply(!!win);"#,
+        helpers = KWIN_WINDOW_HELPERS,
+        pids = to_js(&query.pids),
+        classes = to_js(&query.wm_classes),
+    );
+    Ok(kwin_eval(&script)?.trim() == "true")
+}
+
+fn kwin_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let script = format!(
+        r#"{helpers}
+        const win = activeWindow();
+        reply(JSON.stringify(win ? {{ pid: win.pid, wmClass: String(win.resourceClass) }} : null));"#,
+        helpers = KWIN_WINDOW_HELPERS,
+    );
+    parse_active_window_json(&kwin_eval(&script)?)
+}
+
+struct X11Session {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl X11Session {
+    fn connect() -> Result<Self, AppError> {
+        let (connection, screen) = x11rb::connect(None).map_err(ipc_error)?;
+        let root = connection.setup().roots[screen].root;
+        Ok(Self { connection, root })
+    }
+
+    fn atom(&self, name: &str) -> Result<Atom, AppError> {
+        Ok(self
+            .connection
+            .intern_atom(false, name.as_bytes())
+            .map_err(ipc_error)?
+            .reply()
+            .map_err(ipc_error)?
+            .atom)
+    }
+
+    fn property(
+        &self,
+        window: Window,
+        property: Atom,
+        kind: impl Into<Atom>,
+    ) -> Result<GetPropertyReply, AppError> {
+        self.connection
+            .get_property(false, window, property, kind, 0, u32::MAX / 4)
+            .map_err(ipc_error)?
+            .reply()
+            .map_err(ipc_error)
+    }
+
+    fn window_list(&self) -> Result<Vec<Window>, AppError> {
+        let client_list = self.atom("_NET_CLIENT_LIST")?;
+        Ok(self
+            .property(self.root, client_list, AtomEnum::WINDOW)?
+            .value32()
+            .map(|values| values.collect())
+            .unwrap_or_default())
+    }
+
+    fn window_pid(&self, window: Window) -> Result<Option<u32>, AppError> {
+        let wm_pid = self.atom("_NET_WM_PID")?;
+        Ok(self
+            .property(window, wm_pid, AtomEnum::CARDINAL)?
+            .value32()
+            .and_then(|mut values| values.next()))
+    }
+
+    /// Returns the class part of `WM_CLASS`, which follows the instance name.
+    fn window_class(&self, window: Window) -> Result<Option<String>, AppError> {
+        let reply = self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING)?;
+        Ok(reply
+            .value
+            .split(|byte| *byte == 0)
+            .nth(1)
+            .filter(|class| !class.is_empty())
+            .map(|class| String::from_utf8_lossy(class).into_owned()))
+    }
+
+    fn activate(&self, window: Window) -> Result<(), AppError> {
+        let active_window = self.atom("_NET_ACTIVE_WINDOW")?;
+        // source indication 2 tells the window manager the request comes
+        // from a pager-like tool acting on the user's behalf
+        let event = ClientMessageEvent::new(32, window, active_window, [2, CURRENT_TIME, 0, 0, 0]);
+        self.connection
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(ipc_error)?;
+        self.connection.flush().map_err(ipc_error)?;
+        Ok(())
+    }
+}
+
+fn x11_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let session = X11Session::connect()?;
+    let active_window = session.atom("_NET_ACTIVE_WINDOW")?;
+    let window = session
+        .property(session.root, active_window, AtomEnum::WINDOW)?
+        .value32()
+        .and_then(|mut values| values.next())
+        .filter(|window| *window != 0);
+
+    let Some(window) = window else {
+        return Ok(None);
+    };
+    Ok(Some(ActiveWindow {
+        pid: session.window_pid(window)?,
+        wm_class: session.window_class(window)?,
+    }))
+}
+
+fn x11_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    let session = X11Session::connect()?;
+    for window in session.window_list()? {
+        let pid = session.window_pid(window)?;
+        let class = session.window_class(window)?;
+        if query.matches(pid, class.as_deref()) {
+            session.activate(window)?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    app_id: Option<String>,
+    activated: bool,
+}
+
+/// The toplevel windows announced through `wlr-foreign-toplevel-management`,
+/// which wlroots-based compositors implement. The protocol has no pids.
+#[derive(Default)]
+struct ToplevelList {
+    toplevels: HashMap<ObjectId, Toplevel>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for ToplevelList {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for ToplevelList {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelList {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        _: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+
+    event_created_child!(ToplevelList, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelList {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Event;
+
+        if let Event::Closed = event {
+            state.toplevels.remove(&handle.id());
+            return;
+        }
+        let toplevel = state
+            .toplevels
+            .entry(handle.id())
+            .or_insert_with(|| Toplevel {
+                handle: handle.clone(),
+                app_id: None,
+                activated: false,
+            });
+        match event {
+            Event::AppId { app_id } => toplevel.app_id = Some(app_id),
+            Event::State { state } => {
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                toplevel.activated = state.chunks_exact(4).any(|value| {
+                    u32::from_ne_bytes([value[0], value[1], value[2], value[3]]) == activated
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+struct WlrootsSession {
+    connection: WaylandConnection,
+    seat: Option<wl_seat::WlSeat>,
+    list: ToplevelList,
+}
+
+impl WlrootsSession {
+    fn connect() -> Result<Self, AppError> {
+        let connection = WaylandConnection::connect_to_env().map_err(ipc_error)?;
+        let (globals, mut queue) =
+            registry_queue_init::<ToplevelList>(&connection).map_err(ipc_error)?;
+        let qh = queue.handle();
+
+        let _manager: ZwlrForeignToplevelManagerV1 =
+            globals.bind(&qh, 1..=3, ()).map_err(ipc_error)?;
+        let seat = globals.bind(&qh, 1..=1, ()).ok();
+
+        // the first roundtrip announces the toplevels, the second their details
+        let mut list = ToplevelList::default();
+        queue.roundtrip(&mut list).map_err(ipc_error)?;
+        queue.roundtrip(&mut list).map_err(ipc_error)?;
+
+        Ok(Self {
+            connection,
+            seat,
+            list,
+        })
+    }
+}
+
+fn wlroots_active_window() -> Result<Option<ActiveWindow>, AppError> {
+    let session = WlrootsSession::connect()?;
+    Ok(session
+        .list
+        .toplevels
+        .values()
+        .find(|toplevel| toplevel.activated)
+        .map(|toplevel| ActiveWindow {
+            pid: None,
+            wm_class: toplevel.app_id.clone(),
+        }))
+}
+
+fn wlroots_focus(query: &WindowQuery) -> Result<bool, AppError> {
+    let session = WlrootsSession::connect()?;
+    let Some(seat) = &session.seat else {
+        return Ok(false);
+    };
+    let Some(toplevel) = session
+        .list
+        .toplevels
+        .values()
+        .find(|toplevel| query.matches(None, toplevel.app_id.as_deref()))
+    else {
+        return Ok(false);
+    };
+
+    toplevel.handle.activate(seat);
+    session.connection.flush().map_err(ipc_error)?;
+    Ok(true)
+}
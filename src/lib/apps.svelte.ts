@@ -1,15 +1,30 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { frecencyStore } from './frecency.svelte';
 
-export type App = { name: string; comment?: string; exec: string; icon_path?: string };
+export type App = {
+	name: string;
+	comment?: string;
+	generic_name?: string;
+	keywords: string[];
+	categories: string[];
+	exec: string;
+	icon_path?: string;
+	desktop_file_id?: string;
+	desktop_file_path?: string;
+};
 
 class AppsStore {
 	rawApps = $state<App[]>([]);
+	runningAppIds = $state<string[]>([]);
 	isLoading = $state(true);
 	apps = $derived(this.rawApps.filter((app) => !frecencyStore.hiddenItemIds.includes(app.exec)));
 
 	constructor() {
 		this.fetchApps();
+		listen<App[]>('apps-changed', (event) => {
+			this.rawApps = event.payload;
+		});
 	}
 
 	async fetchApps() {
@@ -23,6 +38,22 @@ class AppsStore {
 			this.isLoading = false;
 		}
 	}
+
+	async fetchRunningApps() {
+		try {
+			const running = await invoke<{ desktopFileId: string; pids: number[] }[]>(
+				'get_running_apps'
+			);
+			this.runningAppIds = running.map((app) => app.desktopFileId);
+		} catch (e) {
+			console.error('Failed to fetch running apps:', e);
+			this.runningAppIds = [];
+		}
+	}
+
+	isRunning(app: App) {
+		return !!app.desktop_file_id && this.runningAppIds.includes(app.desktop_file_id);
+	}
 }
 
 export const appsStore = new AppsStore();
@@ -2,10 +2,14 @@ import type { PluginInfo } from '@raycast-linux/protocol';
 import { invoke } from '@tauri-apps/api/core';
 import Fuse from 'fuse.js';
 import { writeText } from '@tauri-apps/plugin-clipboard-manager';
-import type { Quicklink } from '$lib/quicklinks.svelte';
-import { frecencyStore } from './frecency.svelte';
+import {
+	quicklinksStore,
+	type Quicklink,
+	type QuicklinkArgument
+} from '$lib/quicklinks.svelte';
+import { frecencyStore, type FrecencyDataItem } from './frecency.svelte';
 import { viewManager } from './viewManager.svelte';
-import type { App } from './apps.svelte';
+import { appsStore, type App } from './apps.svelte';
 
 export type UnifiedItem = {
 	type: 'calculator' | 'plugin' | 'app' | 'quicklink';
@@ -19,7 +23,7 @@ type UseCommandPaletteItemsArgs = {
 	plugins: () => PluginInfo[];
 	installedApps: () => App[];
 	quicklinks: () => Quicklink[];
-	frecencyData: () => { itemId: string; useCount: number; lastUsedAt: number }[];
+	frecencyData: () => FrecencyDataItem[];
 	selectedQuicklinkForArgument: () => Quicklink | null;
 };
 
@@ -49,7 +53,12 @@ export function useCommandPaletteItems({
 				'data.description',
 				'data.name',
 				'data.comment',
-				'data.link'
+				'data.generic_name',
+				'data.keywords',
+				'data.categories',
+				'data.link',
+				'data.alias',
+				'data.tags'
 			],
 			threshold: 0.4,
 			includeScore: true
@@ -125,18 +134,27 @@ export function useCommandPaletteItems({
 		}
 
 		const frecencyMap = new Map(frecencyData().map((item) => [item.itemId, item]));
-		const now = Date.now() / 1000;
-		const gravity = 1.8;
+		// frecency scores count roughly one per recent use; picks for the typed
+		// query weigh more than overall use
+		const frecencyWeight = 50;
+		const queryWeight = 200;
+		// typing a quicklink's alias exactly should always bring it up first
+		const aliasMatchScore = 1000;
+		const normalizedTerm = term.trim().toLowerCase();
 
 		items.forEach((item) => {
 			const frecency = frecencyMap.get(item.id);
-			let frecencyScore = 0;
-			if (frecency) {
-				const ageInHours = Math.max(1, (now - frecency.lastUsedAt) / 3600);
-				frecencyScore = (frecency.useCount * 1000) / Math.pow(ageInHours + 2, gravity);
-			}
+			const frecencyScore = frecency
+				? frecency.score * frecencyWeight + frecency.queryScore * queryWeight
+				: 0;
 			const textScore = item.fuseScore !== undefined ? 1 - item.fuseScore * 100 : 0;
-			item.score = frecencyScore + textScore;
+			const aliasScore =
+				item.type === 'quicklink' &&
+				normalizedTerm &&
+				item.data.alias?.toLowerCase() === normalizedTerm
+					? aliasMatchScore
+					: 0;
+			item.score = frecencyScore + textScore + aliasScore;
 		});
 
 		items.sort((a, b) => b.score - a.score);
@@ -165,25 +183,28 @@ export function useCommandPaletteItems({
 
 type UseCommandPaletteActionsArgs = {
 	selectedItem: () => UnifiedItem | undefined;
+	searchText: () => string;
 	onRunPlugin: (plugin: PluginInfo) => void;
 	resetState: () => void;
 	focusArgumentInput: () => void;
+	quicklinkArguments: () => QuicklinkArgument[];
 };
 
 export function useCommandPaletteActions({
 	selectedItem,
+	searchText,
 	onRunPlugin,
 	resetState,
-	focusArgumentInput
+	focusArgumentInput,
+	quicklinkArguments
 }: UseCommandPaletteActionsArgs) {
-	async function executeQuicklink(quicklink: Quicklink, argument?: string) {
-		const finalLink = argument
-			? quicklink.link.replace(/\{argument\}/g, encodeURIComponent(argument))
-			: quicklink.link.replace(/\{argument\}/g, '');
+	async function executeQuicklink(quicklink: Quicklink, args?: Record<string, string>) {
 		await invoke('execute_quicklink', {
-			link: finalLink,
-			application: quicklink.application
+			link: quicklink.link,
+			application: quicklink.application,
+			arguments: args
 		});
+		quicklinksStore.markUsed(quicklink.id);
 		resetState();
 	}
 
@@ -191,7 +212,7 @@ export function useCommandPaletteActions({
 		const item = selectedItem();
 		if (!item) return;
 
-		await frecencyStore.recordUsage(item.id);
+		await frecencyStore.recordUsage(item.id, item.type, searchText());
 
 		switch (item.type) {
 			case 'calculator': {
@@ -210,7 +231,7 @@ export function useCommandPaletteActions({
 			}
 			case 'quicklink': {
 				const quicklink = item.data as Quicklink;
-				if (quicklink.link.includes('{argument}')) {
+				if (quicklinkArguments().length > 0) {
 					focusArgumentInput();
 				} else {
 					executeQuicklink(quicklink);
@@ -263,7 +284,30 @@ export function useCommandPaletteActions({
 	async function handleHideApp() {
 		const item = selectedItem();
 		if (item?.type !== 'app') return;
-		await frecencyStore.hideItem(item.id);
+		await frecencyStore.hideItem(item.id, item.type);
+	}
+
+	async function handleQuitApp(force = false) {
+		const item = selectedItem();
+		if (item?.type !== 'app' || !item.data.desktop_file_id) return;
+		try {
+			await invoke(force ? 'force_quit_app' : 'quit_app', {
+				desktopFileId: item.data.desktop_file_id
+			});
+		} catch (e) {
+			console.error('Failed to quit app:', e);
+		}
+		await appsStore.fetchRunningApps();
+	}
+
+	async function handleRefreshFavicon() {
+		const item = selectedItem();
+		if (item?.type !== 'quicklink') return;
+		try {
+			await quicklinksStore.refreshFavicons(item.data.id);
+		} catch (e) {
+			console.error('Failed to refresh favicon:', e);
+		}
 	}
 
 	return {
@@ -274,6 +318,8 @@ export function useCommandPaletteActions({
 		handleConfigureCommand,
 		handleCopyAppName,
 		handleCopyAppPath,
-		handleHideApp
+		handleHideApp,
+		handleQuitApp,
+		handleRefreshFavicon
 	};
 }
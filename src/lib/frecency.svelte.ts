@@ -1,15 +1,33 @@
 import { invoke } from '@tauri-apps/api/core';
 
-type FrecencyDataItem = {
+export type FrecencyDataItem = {
 	itemId: string;
+	source?: string;
 	useCount: number;
 	lastUsedAt: number;
+	score: number;
+	queryScore: number;
+};
+
+export type HiddenItem = {
+	itemId: string;
+	hiddenAt: number;
+	reason?: string;
+	source?: string;
+};
+
+export type FrecencySettings = {
+	halfLifeHours: number;
+	sourceWeights: Record<string, number>;
 };
 
 class FrecencyStore {
 	data = $state<FrecencyDataItem[]>([]);
 	isLoading = $state(true);
-	hiddenItemIds = $state<string[]>([]);
+	hiddenItems = $state<HiddenItem[]>([]);
+	hiddenItemIds = $derived(this.hiddenItems.map((item) => item.itemId));
+	query = '';
+	private fetchId = 0;
 
 	constructor() {
 		this.fetchData();
@@ -17,9 +35,14 @@ class FrecencyStore {
 	}
 
 	async fetchData() {
+		const fetchId = ++this.fetchId;
 		this.isLoading = true;
 		try {
-			this.data = await invoke<FrecencyDataItem[]>('get_frecency_data');
+			const data = await invoke<FrecencyDataItem[]>('get_ranked_items', {
+				query: this.query || null
+			});
+			if (fetchId !== this.fetchId) return; // Stale request
+			this.data = data;
 		} catch (e) {
 			console.error('Failed to fetch frecency data:', e);
 			this.data = [];
@@ -28,17 +51,23 @@ class FrecencyStore {
 		}
 	}
 
+	setQuery(query: string) {
+		if (query === this.query) return;
+		this.query = query;
+		this.fetchData();
+	}
+
 	async fetchHiddenItems() {
 		try {
-			this.hiddenItemIds = await invoke<string[]>('get_hidden_item_ids');
+			this.hiddenItems = await invoke<HiddenItem[]>('get_hidden_items');
 		} catch (e) {
 			console.error('Failed to fetch hidden items:', e);
 		}
 	}
 
-	async recordUsage(itemId: string) {
+	async recordUsage(itemId: string, source?: string, query?: string) {
 		try {
-			await invoke('record_usage', { itemId });
+			await invoke('record_usage', { itemId, source, query });
 			this.fetchData();
 		} catch (e) {
 			console.error(`Failed to record usage for ${itemId}:`, e);
@@ -54,14 +83,41 @@ class FrecencyStore {
 		}
 	}
 
-	async hideItem(itemId: string) {
+	async getSettings() {
+		return invoke<FrecencySettings>('get_frecency_settings');
+	}
+
+	async setSettings(settings: FrecencySettings) {
+		await invoke('set_frecency_settings', { settings });
+		await this.fetchData();
+	}
+
+	async hideItem(itemId: string, source?: string, reason = 'user') {
 		try {
-			await invoke('hide_item', { itemId });
+			await invoke('hide_item', { itemId, reason, source });
 			await this.fetchHiddenItems();
 		} catch (e) {
 			console.error(`Failed to hide item ${itemId}:`, e);
 		}
 	}
+
+	async unhideItems(itemIds: string[]) {
+		try {
+			await invoke('unhide_items', { itemIds });
+			await Promise.all([this.fetchHiddenItems(), this.fetchData()]);
+		} catch (e) {
+			console.error('Failed to unhide items:', e);
+		}
+	}
+
+	async resetRankingData() {
+		try {
+			await invoke('reset_ranking_data');
+			await this.fetchData();
+		} catch (e) {
+			console.error('Failed to reset ranking data:', e);
+		}
+	}
 }
 
 export const frecencyStore = new FrecencyStore();
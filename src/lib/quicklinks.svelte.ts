@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 export type Quicklink = {
 	id: number;
@@ -8,6 +9,24 @@ export type Quicklink = {
 	icon: string | null;
 	createdAt: string;
 	updatedAt: string;
+	alias: string | null;
+	tags: string[];
+	timesUsed: number;
+	lastUsedAt: string;
+};
+
+type QuicklinkData = {
+	name: string;
+	link: string;
+	application?: string;
+	icon?: string;
+	alias?: string;
+	tags?: string[];
+};
+
+export type QuicklinkArgument = {
+	name: string;
+	default: string | null;
 };
 
 class QuicklinksStore {
@@ -17,6 +36,10 @@ class QuicklinksStore {
 
 	constructor() {
 		this.fetchQuicklinks();
+		// favicons are fetched in the background after a quicklink is saved
+		listen('quicklinks-changed', () => {
+			this.fetchQuicklinks();
+		});
 	}
 
 	async fetchQuicklinks() {
@@ -33,7 +56,7 @@ class QuicklinksStore {
 		}
 	}
 
-	async create(data: { name: string; link: string; application?: string; icon?: string }) {
+	async create(data: QuicklinkData) {
 		try {
 			await invoke('create_quicklink', data);
 			await this.fetchQuicklinks();
@@ -43,10 +66,7 @@ class QuicklinksStore {
 		}
 	}
 
-	async update(
-		id: number,
-		data: { name: string; link: string; application?: string; icon?: string }
-	) {
+	async update(id: number, data: QuicklinkData) {
 		try {
 			await invoke('update_quicklink', { id, ...data });
 			await this.fetchQuicklinks();
@@ -56,6 +76,26 @@ class QuicklinksStore {
 		}
 	}
 
+	async findByAlias(alias: string) {
+		return invoke<Quicklink | null>('get_quicklink_by_alias', { alias });
+	}
+
+	async markUsed(id: number) {
+		try {
+			await invoke('quicklink_was_used', { id });
+		} catch (e) {
+			console.error('Failed to record quicklink use:', e);
+		}
+	}
+
+	async refreshFavicons(id?: number) {
+		return invoke<number>('refresh_quicklink_favicons', { id });
+	}
+
+	async getArguments(id: number) {
+		return invoke<QuicklinkArgument[]>('get_quicklink_arguments', { id });
+	}
+
 	async delete(id: number) {
 		try {
 			await invoke('delete_quicklink', { id });
@@ -5,6 +5,7 @@ import { CommandSchema, SidecarMessageWithPluginsSchema } from '@raycast-linux/p
 import { invoke } from '@tauri-apps/api/core';
 import { appCacheDir, appLocalDataDir } from '@tauri-apps/api/path';
 import { listen } from '@tauri-apps/api/event';
+import { appsStore } from '$lib/apps.svelte';
 
 type OauthState = {
 	url: string;
@@ -185,7 +186,19 @@ class SidecarService {
 
 		if (typedMessage.type === 'open') {
 			const { target, application } = typedMessage.payload;
-			shellOpen(target, application).catch((err) => {
+			// applications from getApplications() are identified by their desktop file
+			const app = application
+				? appsStore.rawApps.find(
+						(a) =>
+							a.desktop_file_path === application ||
+							a.desktop_file_id === application ||
+							a.exec === application
+					)
+				: undefined;
+			const opening = app
+				? invoke('launch_app', { exec: app.exec, targets: [target] })
+				: shellOpen(target, application);
+			opening.catch((err) => {
 				this.#log(`ERROR: Failed to open '${target}': ${err}`);
 				console.error(`Failed to open '${target}':`, err);
 			});
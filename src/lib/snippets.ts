@@ -0,0 +1,21 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export type TriggerMode = 'immediate' | 'wordBoundary' | 'delimiter';
+
+export type SnippetSettings = {
+	trigger: TriggerMode;
+};
+
+export const triggerModeLabels: Record<TriggerMode, string> = {
+	immediate: 'Immediately',
+	wordBoundary: 'After a whole word',
+	delimiter: 'After a space or punctuation'
+};
+
+export function getSnippetSettings() {
+	return invoke<SnippetSettings>('get_snippet_settings');
+}
+
+export function setSnippetSettings(settings: SnippetSettings) {
+	return invoke('set_snippet_settings', { settings });
+}
@@ -3,6 +3,7 @@ import { uiStore } from '$lib/ui.svelte';
 import { sidecarService } from '$lib/sidecar.svelte';
 import type { Quicklink } from './quicklinks.svelte';
 import { invoke } from '@tauri-apps/api/core';
+import { save } from '@tauri-apps/plugin-dialog';
 
 export type ViewState =
 	| 'command-palette'
@@ -14,7 +15,9 @@ export type ViewState =
 	| 'quicklink-form'
 	| 'create-snippet-form'
 	| 'import-snippets'
-	| 'file-search';
+	| 'import-quicklinks'
+	| 'file-search'
+	| 'trash';
 
 type OauthState = {
 	url: string;
@@ -72,10 +75,33 @@ class ViewManager {
 		this.currentView = 'import-snippets';
 	};
 
+	showImportQuicklinks = () => {
+		this.currentView = 'import-quicklinks';
+	};
+
+	exportQuicklinks = async () => {
+		const path = await save({
+			defaultPath: 'quicklinks.json',
+			filters: [{ name: 'JSON', extensions: ['json'] }]
+		});
+		if (!path) return;
+		try {
+			const count = await invoke<number>('export_quicklinks', { path });
+			await invoke('show_hud', { title: `Exported ${count} Quicklinks` });
+		} catch (e) {
+			console.error('Failed to export quicklinks:', e);
+			await invoke('show_hud', { title: 'Failed to Export Quicklinks' });
+		}
+	};
+
 	showFileSearch = () => {
 		this.currentView = 'file-search';
 	};
 
+	showTrash = () => {
+		this.currentView = 'trash';
+	};
+
 	runPlugin = async (plugin: PluginInfo) => {
 		switch (plugin.pluginPath) {
 			case 'builtin:store':
@@ -96,9 +122,18 @@ class ViewManager {
 			case 'builtin:import-snippets':
 				this.showImportSnippets();
 				return;
+			case 'builtin:import-quicklinks':
+				this.showImportQuicklinks();
+				return;
+			case 'builtin:export-quicklinks':
+				this.exportQuicklinks();
+				return;
 			case 'builtin:file-search':
 				this.showFileSearch();
 				return;
+			case 'builtin:trash':
+				this.showTrash();
+				return;
 		}
 
 		uiStore.setCurrentRunningPlugin(plugin);